@@ -0,0 +1,94 @@
+// RgbaColor is a gamma encoded sRGB color with alpha, each channel in 0..1. This is what every
+// color model renders into, quantized views and hex formatting are built on top of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RgbaColor {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub alpha: f32,
+}
+
+impl RgbaColor {
+    pub const TRANSPARENT: RgbaColor = RgbaColor {
+        red: 0.0,
+        green: 0.0,
+        blue: 0.0,
+        alpha: 0.0,
+    };
+
+    // channels returns the channels in RGBA order
+    pub fn channels(self) -> [f32; 4] {
+        [self.red, self.green, self.blue, self.alpha]
+    }
+
+    // to_u8 quantizes each channel to 0..=255, rounding to the nearest step
+    pub fn to_u8(self) -> [u8; 4] {
+        self.channels().map(|channel| quantize(channel, u8::MAX as f32) as u8)
+    }
+
+    // to_u16 quantizes each channel to 0..=65535, rounding to the nearest step
+    pub fn to_u16(self) -> [u16; 4] {
+        self.channels().map(|channel| quantize(channel, u16::MAX as f32) as u16)
+    }
+
+    // to_rgba_u32 packs the 8 bit channels into a #rrggbbaa u32
+    pub fn to_rgba_u32(self) -> u32 {
+        u32::from_be_bytes(self.to_u8())
+    }
+
+    // to_hex formats the color as #rrggbb, dropping alpha
+    pub fn to_hex(self) -> String {
+        let [red, green, blue, _] = self.to_u8();
+        format!("#{:02x}{:02x}{:02x}", red, green, blue)
+    }
+
+    // to_hex_alpha formats the color as #rrggbbaa
+    pub fn to_hex_alpha(self) -> String {
+        format!("#{:08x}", self.to_rgba_u32())
+    }
+}
+
+// quantize scales a 0..1 channel to 0..=max, clamping it first so out of gamut values saturate
+// rather than wrap
+fn quantize(channel: f32, max: f32) -> f32 {
+    (channel.clamp(0.0, 1.0) * max).round()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(red: f32, green: f32, blue: f32, alpha: f32) -> RgbaColor {
+        RgbaColor {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    #[test]
+    fn rounds_to_the_nearest_step() {
+        assert_eq!(rgba(0.5, 0.0, 1.0, 0.25).to_u8(), [128, 0, 255, 64]);
+        assert_eq!(rgba(0.5, 0.0, 1.0, 1.0).to_u16(), [32768, 0, 65535, 65535]);
+    }
+
+    #[test]
+    fn clamps_out_of_gamut_channels() {
+        assert_eq!(rgba(-0.2, 1.3, -0.0, 2.0).to_u8(), [0, 255, 0, 255]);
+        assert_eq!(rgba(-1.0, 10.0, 0.0, 1.0).to_u16(), [0, 65535, 0, 65535]);
+    }
+
+    #[test]
+    fn packs_rgba_in_order() {
+        let color = rgba(1.0, 0.5, 0.0, 0.2);
+        assert_eq!(color.to_rgba_u32(), 0xff80_0033);
+        assert_eq!(color.to_hex_alpha(), "#ff800033");
+    }
+
+    #[test]
+    fn hex_drops_alpha() {
+        assert_eq!(rgba(1.0, 0.5, 0.0, 0.2).to_hex(), "#ff8000");
+        assert_eq!(rgba(0.0, 0.0, 0.0, 0.0).to_hex(), "#000000");
+    }
+}
@@ -14,5 +14,6 @@
  *
  */
 
+pub mod color;
 pub mod model;
 pub mod palette_models;
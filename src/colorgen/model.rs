@@ -1,23 +1,139 @@
+use super::color::RgbaColor;
+use anyhow::{bail, Context as _};
 use bevy::prelude::*;
-use palette::{
-    convert::FromColorUnclamped, convert::IntoColor, rgb::Rgb, stimulus::FromStimulus, Clamp,
-};
-use std::collections::HashMap;
+use palette::{convert::IntoColor, rgb::Rgb, Alpha};
+use std::collections::{BTreeMap, HashMap};
 use std::marker::PhantomData;
 
+// Unit describes the scale an input's value is written in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Degrees,
+    Percent,
+    UnitInterval,
+    Number,
+}
+
+impl Unit {
+    // scale is the factor applied to a value in this unit to get the value the color space takes
+    pub fn scale(self) -> f32 {
+        match self {
+            Unit::Percent => 0.01,
+            Unit::Degrees | Unit::UnitInterval | Unit::Number => 1.0,
+        }
+    }
+}
+
+// OutOfRange is the policy applied to an input value that falls outside of its range
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutOfRange {
+    Clamp,
+    Wrap,
+    Error,
+}
+
+// ModelInput is a single input of a color space, along with the constraints on its value
+#[derive(Clone, Debug)]
+pub struct ModelInput {
+    // name is the variable the input is assigned to in expressions
+    pub name: String,
+
+    // min and max bound the input's value, in its unit
+    pub min: f32,
+    pub max: f32,
+
+    pub unit: Unit,
+    pub out_of_range: OutOfRange,
+
+    // default is used in place of an empty expression, inputs without one must always be written
+    pub default: Option<f32>,
+}
+
+impl ModelInput {
+    // hue returns an input for an angle in degrees that wraps around at 360
+    pub fn hue(name: &str) -> ModelInput {
+        ModelInput {
+            name: name.into(),
+            min: 0.0,
+            max: 360.0,
+            unit: Unit::Degrees,
+            out_of_range: OutOfRange::Wrap,
+            default: None,
+        }
+    }
+
+    // unit_interval returns an input that must stay within 0..1
+    pub fn unit_interval(name: &str) -> ModelInput {
+        ModelInput {
+            name: name.into(),
+            min: 0.0,
+            max: 1.0,
+            unit: Unit::UnitInterval,
+            out_of_range: OutOfRange::Error,
+            default: None,
+        }
+    }
+
+    // number returns an input for a plain number that is clamped to min..max
+    pub fn number(name: &str, min: f32, max: f32) -> ModelInput {
+        ModelInput {
+            name: name.into(),
+            min,
+            max,
+            unit: Unit::Number,
+            out_of_range: OutOfRange::Clamp,
+            default: None,
+        }
+    }
+
+    // alpha returns an optional opacity input in 0..1 that is fully opaque when left empty
+    pub fn alpha(name: &str) -> ModelInput {
+        ModelInput {
+            default: Some(1.0),
+            ..ModelInput::unit_interval(name)
+        }
+    }
+
+    // constrain applies the out-of-range policy to a value in the input's unit. The result stays
+    // in that unit, so that expressions reading it back see what they wrote, and is only scaled
+    // for the color space when the color is converted.
+    pub fn constrain(&self, value: f32) -> anyhow::Result<f32> {
+        let constrained = if (self.min..=self.max).contains(&value) {
+            value
+        } else {
+            match self.out_of_range {
+                OutOfRange::Clamp => value.clamp(self.min, self.max),
+                OutOfRange::Wrap => self.min + (value - self.min).rem_euclid(self.max - self.min),
+                OutOfRange::Error => bail!(
+                    "input `{}` value {} is outside of {}..={}",
+                    self.name,
+                    value,
+                    self.min,
+                    self.max
+                ),
+            }
+        };
+
+        Ok(constrained)
+    }
+}
+
 pub trait ColorSpaceData {
     // inputs provides the inputs this color space requires
-    fn inputs(&self) -> &Vec<String>;
+    fn inputs(&self) -> &Vec<ModelInput>;
 
     // name provides a user-facing name for this colorspace
-    fn name(self) -> String;
+    fn name(&self) -> &str;
 }
 
 pub trait AsRGBA {
-    // converts the color space data into a #rrggbbaa hexcode, as an u32
-    fn hsv_as_rgba_hex(self, hsv_components: [f32; 3]) -> u32;
-    fn as_rgba_hex(&self, input_vals: &HashMap<String, f32>) -> u32;
-    fn many_as_rgba_hex(self, input_vals_vec: Vec<&HashMap<String, f32>>) -> Vec<u32>;
+    // converts the color space data into an sRGB color with alpha, failing if a required input is
+    // missing from input_vals. Values are given in the unit of their input.
+    fn as_rgba(&self, input_vals: &HashMap<String, f32>) -> anyhow::Result<RgbaColor>;
+    fn many_as_rgba(
+        &self,
+        input_vals_vec: Vec<&HashMap<String, f32>>,
+    ) -> anyhow::Result<Vec<RgbaColor>>;
 }
 
 pub trait FromHSVLikeVals<T> {
@@ -25,19 +141,63 @@ pub trait FromHSVLikeVals<T> {
     fn from_hsv_like(self) -> T;
 }
 
-pub trait PaletteColorSpace:
-    Copy
-    + Into<[f32; 4]>
-    + IntoColor<Rgb>
-    + Clamp
-    + FromColorUnclamped<std::vec::Vec<f32>>
-    + FromColorUnclamped<[f32; 3]>
+// PaletteColorSpace is implemented for any Palette colorspace that can be built from its three
+// components and converted into RGB
+pub trait PaletteColorSpace: Copy + IntoColor<Rgb> + From<[f32; 3]> + Send + Sync + 'static {}
+
+impl<T> PaletteColorSpace for T where
+    T: Copy + IntoColor<Rgb> + From<[f32; 3]> + Send + Sync + 'static
 {
 }
 
-pub trait ColorSpace: AsRGBA + ColorSpaceData {}
+pub trait ColorSpace: AsRGBA + ColorSpaceData + Send + Sync {}
+
+// ColorSpaceRegistry holds every color space available at runtime keyed by name, along with the
+// name of the one currently in use
+#[derive(Resource)]
+pub struct ColorSpaceRegistry {
+    pub color_spaces: BTreeMap<String, Box<dyn ColorSpace>>,
+    active: String,
+}
+
+impl ColorSpaceRegistry {
+    // new returns a registry containing only the given color space, which is made active
+    pub fn new(color_space: impl ColorSpace + 'static) -> ColorSpaceRegistry {
+        let active = color_space.name().to_string();
+        let mut registry = ColorSpaceRegistry {
+            color_spaces: BTreeMap::new(),
+            active,
+        };
+        registry.register(color_space);
 
-pub struct ColorSpaceRes(dyn ColorSpace);
+        registry
+    }
+
+    // register adds a color space under its name, replacing any registered with the same name
+    pub fn register(&mut self, color_space: impl ColorSpace + 'static) {
+        self.color_spaces
+            .insert(color_space.name().to_string(), Box::new(color_space));
+    }
+
+    // active returns the color space currently in use
+    pub fn active(&self) -> &dyn ColorSpace {
+        self.color_spaces[&self.active].as_ref()
+    }
+
+    pub fn active_name(&self) -> &str {
+        &self.active
+    }
+
+    // set_active switches the color space in use to the one registered under name
+    pub fn set_active(&mut self, name: &str) -> anyhow::Result<()> {
+        if !self.color_spaces.contains_key(name) {
+            bail!("no color model named `{}` is registered", name);
+        }
+        self.active = name.to_string();
+
+        Ok(())
+    }
+}
 
 // generic colorspace for wrapping a Palette provided colorspace
 pub struct GenericPaletteSpace<T: PaletteColorSpace> {
@@ -46,65 +206,140 @@ pub struct GenericPaletteSpace<T: PaletteColorSpace> {
 
     // inputs is necessary so that we have an ordered list of the
     // components a Palette colorspace takes and can use
-    // from_components()
-    pub inputs: Vec<String>,
+    // from_components(). An alpha input may follow the three components.
+    pub inputs: Vec<ModelInput>,
 
     // _palette_color_space exists to identify the Palette colorspace of this GenericPaletteSpace
     pub _palette_color_space: PhantomData<T>,
 }
 
+impl<T: PaletteColorSpace> GenericPaletteSpace<T> {
+    // with_alpha adds an optional alpha input after the components, named `a` unless one of the
+    // components already uses `a`, in which case it's named `alpha`
+    pub fn with_alpha(mut self) -> Self {
+        let name = if self.inputs.iter().any(|input| input.name == "a") {
+            "alpha"
+        } else {
+            "a"
+        };
+        self.inputs.push(ModelInput::alpha(name));
+
+        self
+    }
+}
+
 impl<T: PaletteColorSpace> ColorSpaceData for GenericPaletteSpace<T> {
-    fn inputs(&self) -> &Vec<String> {
+    fn inputs(&self) -> &Vec<ModelInput> {
         return &self.inputs;
     }
 
-    fn name(self) -> String {
-        return self.name;
+    fn name(&self) -> &str {
+        return &self.name;
     }
 }
 
 impl<T: PaletteColorSpace> AsRGBA for GenericPaletteSpace<T> {
-    fn hsv_as_rgba_hex(self, hsv_components: [f32; 3]) -> u32 {
-        let rgb_color: Rgb = Rgb::from(hsv_components);
-        let rgb_bytes: [f32; 3] = rgb_color.into();
-
-        (rgb_bytes[0] as u32) << 24
-            | (rgb_bytes[1] as u32) << 16
-            | (rgb_bytes[2] as u32) << 8
-            | 0xff
-    }
-
-    fn as_rgba_hex(&self, input_vals: &HashMap<String, f32>) -> u32 {
-        let mut component_vec: Vec<f32> = vec![];
-        for input in &self.inputs {
-            if let Some(input_val) = input_vals.get(input) {
-                component_vec.push(*input_val);
-            }
+    fn as_rgba(&self, input_vals: &HashMap<String, f32>) -> anyhow::Result<RgbaColor> {
+        // components are placed by the position of their input so that they line up with the
+        // order the Palette colorspace expects
+        let mut components: [f32; 3] = [0.0; 3];
+        for (component, input) in components.iter_mut().zip(&self.inputs) {
+            *component = input_value(input, input_vals)?;
         }
 
-        let color: T = component_vec.into_color();
+        let alpha = match self.inputs.get(3) {
+            Some(input) => input_value(input, input_vals)?,
+            None => 1.0,
+        };
+        let color = Alpha {
+            color: T::from(components),
+            alpha,
+        };
 
-        color_as_rgb(color)
+        Ok(color_as_rgba(color))
     }
 
-    fn many_as_rgba_hex(self, input_vals_vec: Vec<&HashMap<String, f32>>) -> Vec<u32> {
-        let mut hex_vec: Vec<u32> = vec![];
-        for input_vals in input_vals_vec {
-            hex_vec.push(self.as_rgba_hex(input_vals));
+    fn many_as_rgba(
+        &self,
+        input_vals_vec: Vec<&HashMap<String, f32>>,
+    ) -> anyhow::Result<Vec<RgbaColor>> {
+        let mut color_vec: Vec<RgbaColor> = vec![];
+        for (i, input_vals) in input_vals_vec.into_iter().enumerate() {
+            color_vec.push(
+                self.as_rgba(input_vals)
+                    .with_context(|| format!("failed to convert color {}", i))?,
+            );
         }
 
-        hex_vec
+        Ok(color_vec)
     }
 }
 
-fn color_as_rgb<T: PaletteColorSpace>(color: T) -> u32 {
-    let rgb_color: Rgb = color.into_color();
-    let rgb_bytes: [f32; 3] = rgb_color.into();
+// input_value looks up the value of an input, falling back to its default if it has one, and
+// scales it from the input's unit to the value the color space takes
+fn input_value(input: &ModelInput, input_vals: &HashMap<String, f32>) -> anyhow::Result<f32> {
+    let value = match (input_vals.get(&input.name), input.default) {
+        (Some(input_val), _) => *input_val,
+        (None, Some(default)) => default,
+        (None, None) => bail!("input `{}` is missing", input.name),
+    };
 
-    (rgb_bytes[0] as u32) << 24 | (rgb_bytes[1] as u32) << 16 | (rgb_bytes[2] as u32) << 8 | 0xff
+    Ok(value * input.unit.scale())
+}
+
+fn color_as_rgba<T: PaletteColorSpace>(color: Alpha<T, f32>) -> RgbaColor {
+    // Rgb defaults to gamma encoded sRGB, which every model is converted into for output
+    let rgb_color: Rgb = color.color.into_color();
+
+    RgbaColor {
+        red: rgb_color.red,
+        green: rgb_color.green,
+        blue: rgb_color.blue,
+        alpha: color.alpha,
+    }
 }
 
 // impl<T: PaletteColorSpace> ColorSpace for T { }
 
 // impl<T: PaletteColorSpace> ColorSpace for GenericPaletteSpace<T> where T: AsRGBA + ColorSpaceData {}
 impl<T: PaletteColorSpace> ColorSpace for GenericPaletteSpace<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_hues_around_the_circle() {
+        let hue = ModelInput::hue("h");
+        for (value, wrapped) in [(-30.0, 330.0), (-360.0, 0.0), (370.0, 10.0), (725.0, 5.0)] {
+            assert!((hue.constrain(value).unwrap() - wrapped).abs() < 1e-4, "{}", value);
+        }
+        assert_eq!(hue.constrain(360.0).unwrap(), 360.0);
+    }
+
+    #[test]
+    fn clamps_numbers_to_their_range() {
+        let chroma = ModelInput::number("c", 0.0, 0.4);
+        assert_eq!(chroma.constrain(-1.0).unwrap(), 0.0);
+        assert_eq!(chroma.constrain(0.25).unwrap(), 0.25);
+        assert_eq!(chroma.constrain(2.0).unwrap(), 0.4);
+    }
+
+    #[test]
+    fn rejects_unit_intervals_out_of_range() {
+        let saturation = ModelInput::unit_interval("s");
+        assert_eq!(saturation.constrain(1.0).unwrap(), 1.0);
+        assert!(saturation.constrain(1.2).is_err());
+        assert!(saturation.constrain(-0.1).is_err());
+    }
+
+    #[test]
+    fn keeps_values_in_their_unit() {
+        let percent = ModelInput {
+            unit: Unit::Percent,
+            ..ModelInput::number("p", 0.0, 100.0)
+        };
+        assert_eq!(percent.constrain(150.0).unwrap(), 100.0);
+        assert_eq!(percent.unit.scale() * percent.constrain(50.0).unwrap(), 0.5);
+    }
+}
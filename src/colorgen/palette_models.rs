@@ -1,21 +1,136 @@
-use super::model::{GenericPaletteSpace, ColorSpace};
+use super::model::{ColorSpaceRegistry, GenericPaletteSpace, ModelInput};
+use palette::{white_point::D65, Hsl, Hwb, Lab, Lch, LinSrgb, Srgb};
 use std::marker::PhantomData;
 
 use bevy::prelude::*;
 
-#[derive(Resource)]
-pub struct HsvColorSpace(pub GenericPaletteSpace<palette::hsv::Hsv>);
-
+// setup_model_resources inserts the registry of every color model, with hsv active. Each model is
+// registered with an optional alpha input.
 pub fn setup_model_resources(mut commands: Commands) {
-    commands.insert_resource(hsv());
+    let mut registry = ColorSpaceRegistry::new(hsv().with_alpha());
+    registry.register(oklab().with_alpha());
+    registry.register(oklch().with_alpha());
+    registry.register(lab().with_alpha());
+    registry.register(lch().with_alpha());
+    registry.register(hsl().with_alpha());
+    registry.register(hwb().with_alpha());
+    registry.register(srgb().with_alpha());
+    registry.register(linear_rgb().with_alpha());
+    commands.insert_resource(registry);
 }
 
-pub fn hsv() -> HsvColorSpace {
-    HsvColorSpace(GenericPaletteSpace {
+pub fn hsv() -> GenericPaletteSpace<palette::hsv::Hsv> {
+    GenericPaletteSpace {
         name: "hsv".into(),
-        inputs: vec!["h".into(), "s".into(), "v".into()],
+        inputs: vec![
+            ModelInput::hue("h"),
+            ModelInput::unit_interval("s"),
+            ModelInput::unit_interval("v"),
+        ],
+        _palette_color_space: PhantomData,
+    }
+}
+
+// oklab's a and b are clamped to ±0.4, a loose bound around the sRGB gamut (which reaches a in
+// about -0.23..0.28 and b in about -0.31..0.20) that leaves room for wider gamut colors
+pub fn oklab() -> GenericPaletteSpace<palette::Oklab> {
+    GenericPaletteSpace {
+        name: "oklab".into(),
+        inputs: vec![
+            ModelInput::unit_interval("l"),
+            ModelInput::number("a", -0.4, 0.4),
+            ModelInput::number("b", -0.4, 0.4),
+        ],
+        _palette_color_space: PhantomData,
+    }
+}
+
+pub fn oklch() -> GenericPaletteSpace<palette::Oklch> {
+    GenericPaletteSpace {
+        name: "oklch".into(),
+        inputs: vec![
+            ModelInput::unit_interval("l"),
+            ModelInput::number("c", 0.0, 0.4),
+            ModelInput::hue("h"),
+        ],
+        _palette_color_space: PhantomData,
+    }
+}
+
+// lab and lch are relative to the D65 white point, the same one sRGB output uses, so no chromatic
+// adaptation happens on the way to RGB
+pub fn lab() -> GenericPaletteSpace<Lab<D65, f32>> {
+    GenericPaletteSpace {
+        name: "lab".into(),
+        inputs: vec![
+            ModelInput::number("l", 0.0, 100.0),
+            ModelInput::number("a", -128.0, 127.0),
+            ModelInput::number("b", -128.0, 127.0),
+        ],
+        _palette_color_space: PhantomData,
+    }
+}
+
+pub fn lch() -> GenericPaletteSpace<Lch<D65, f32>> {
+    GenericPaletteSpace {
+        name: "lch".into(),
+        inputs: vec![
+            ModelInput::number("l", 0.0, 100.0),
+            ModelInput::number("c", 0.0, 150.0),
+            ModelInput::hue("h"),
+        ],
+        _palette_color_space: PhantomData,
+    }
+}
+
+pub fn hsl() -> GenericPaletteSpace<Hsl> {
+    GenericPaletteSpace {
+        name: "hsl".into(),
+        inputs: vec![
+            ModelInput::hue("h"),
+            ModelInput::unit_interval("s"),
+            ModelInput::unit_interval("l"),
+        ],
+        _palette_color_space: PhantomData,
+    }
+}
+
+pub fn hwb() -> GenericPaletteSpace<Hwb> {
+    GenericPaletteSpace {
+        name: "hwb".into(),
+        inputs: vec![
+            ModelInput::hue("h"),
+            ModelInput::unit_interval("w"),
+            ModelInput::unit_interval("b"),
+        ],
+        _palette_color_space: PhantomData,
+    }
+}
+
+// srgb takes its components already gamma encoded, so they are output exactly as written
+pub fn srgb() -> GenericPaletteSpace<Srgb> {
+    GenericPaletteSpace {
+        name: "srgb".into(),
+        inputs: vec![
+            ModelInput::unit_interval("r"),
+            ModelInput::unit_interval("g"),
+            ModelInput::unit_interval("b"),
+        ],
+        _palette_color_space: PhantomData,
+    }
+}
+
+// linear_rgb takes linear light components, which go through the sRGB transfer function on output
+pub fn linear_rgb() -> GenericPaletteSpace<LinSrgb> {
+    GenericPaletteSpace {
+        name: "linear_rgb".into(),
+        inputs: vec![
+            ModelInput::unit_interval("r"),
+            ModelInput::unit_interval("g"),
+            ModelInput::unit_interval("b"),
+        ],
         _palette_color_space: PhantomData,
-    })
+    }
 }
 
 // impl ColorSpace for GenericPaletteSpace<palette::hsv::Hsv> {}
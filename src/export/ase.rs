@@ -0,0 +1,457 @@
+use super::super::colorgen::color::RgbaColor;
+use super::super::colorgen::model::ColorSpaceRegistry;
+use super::super::expr::parse::Palette;
+use super::swatch_name;
+use anyhow::{bail, Context as _};
+use std::collections::HashMap;
+use std::path::Path;
+
+const SIGNATURE: &[u8; 4] = b"ASEF";
+const GROUP_START: u16 = 0xc001;
+const GROUP_END: u16 = 0xc002;
+const COLOR_ENTRY: u16 = 0x0001;
+
+// AseColor is the color of a swatch in the model it was written with. Lab stores L in 0..1 and a
+// and b in -128..127, every other model stores its channels in 0..1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AseColor {
+    Rgb([f32; 3]),
+    Lab([f32; 3]),
+    Cmyk([f32; 4]),
+    Gray(f32),
+}
+
+// AseColorType is how Adobe applications link a swatch to the artwork using it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AseColorType {
+    Global,
+    Spot,
+    Normal,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AseSwatch {
+    pub name: String,
+    pub color: AseColor,
+    pub color_type: AseColorType,
+}
+
+// AseGroup is a named group of swatches, swatches outside of any group are kept in a group with
+// an empty name
+#[derive(Clone, Debug, PartialEq)]
+pub struct AseGroup {
+    pub name: String,
+    pub swatches: Vec<AseSwatch>,
+}
+
+// ImportedGroup is a group of an imported swatch file
+pub struct ImportedGroup {
+    pub name: String,
+    pub swatches: Vec<ImportedSwatch>,
+}
+
+// ImportedSwatch keeps the color of a swatch in the model it was written with, along with its
+// conversion to RGBA for previewing
+pub struct ImportedSwatch {
+    pub name: String,
+    pub color: AseColor,
+    pub preview: RgbaColor,
+}
+
+impl AseColor {
+    // to_rgba converts the color to RGBA. Lab is rendered through the `lab` color model of
+    // registry so it matches what the expressions produce, CMYK is converted naively since no
+    // color profile is known.
+    pub fn to_rgba(&self, registry: &ColorSpaceRegistry) -> anyhow::Result<RgbaColor> {
+        let rgb = |[red, green, blue]: [f32; 3]| RgbaColor {
+            red,
+            green,
+            blue,
+            alpha: 1.0,
+        };
+
+        match *self {
+            AseColor::Rgb(channels) => Ok(rgb(channels)),
+            AseColor::Lab([l, a, b]) => {
+                let model = registry
+                    .color_spaces
+                    .get("lab")
+                    .context("Lab swatches need the `lab` color model")?;
+                let inputs: HashMap<String, f32> = [("l", l * 100.0), ("a", a), ("b", b)]
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value))
+                    .collect();
+                model.as_rgba(&inputs)
+            }
+            AseColor::Cmyk([c, m, y, k]) => {
+                Ok(rgb([c, m, y].map(|channel| (1.0 - channel) * (1.0 - k))))
+            }
+            AseColor::Gray(gray) => Ok(rgb([gray; 3])),
+        }
+    }
+}
+
+// palette_to_ase groups the colors of palette by row, naming each group and swatch after the
+// palette. Palettes rendered with the `lab` or `lch` models are written as Lab, everything else as
+// RGB. ASE has no alpha so it's dropped.
+pub fn palette_to_ase(palette: &Palette, name: &str) -> Vec<AseGroup> {
+    let columns = palette.columns.max(1) as usize;
+    palette
+        .rows_iter()
+        .enumerate()
+        .map(|(y, row)| AseGroup {
+            name: if palette.rows > 1 {
+                format!("{} {}", name, y)
+            } else {
+                name.to_string()
+            },
+            swatches: row
+                .iter()
+                .enumerate()
+                .map(|(x, color)| AseSwatch {
+                    name: swatch_name(palette, name, y * columns + x),
+                    color: ase_color(palette, y * columns + x, *color),
+                    color_type: AseColorType::Normal,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+// ase_color picks the color written for color index of palette, falling back to RGB when the
+// index has no Lab inputs
+fn ase_color(palette: &Palette, index: usize, color: RgbaColor) -> AseColor {
+    let input = |name: &str| palette.inputs.get(index)?.get(name).copied();
+    let lab = match palette.model.as_str() {
+        "lab" => input("l").zip(input("a")).zip(input("b")),
+        "lch" => input("l").zip(input("c")).zip(input("h")).map(|((l, c), h)| {
+            let (sin, cos) = h.to_radians().sin_cos();
+            ((l, c * cos), c * sin)
+        }),
+        _ => None,
+    };
+
+    match lab {
+        Some(((l, a), b)) => AseColor::Lab([l / 100.0, a, b]),
+        None => AseColor::Rgb([color.red, color.green, color.blue]),
+    }
+}
+
+// write_ase encodes groups as an ASE file
+pub fn write_ase(groups: &[AseGroup]) -> Vec<u8> {
+    let mut blocks: Vec<(u16, Vec<u8>)> = vec![];
+    for group in groups {
+        let named = !group.name.is_empty();
+        if named {
+            let mut block = vec![];
+            write_name(&mut block, &group.name);
+            blocks.push((GROUP_START, block));
+        }
+        for swatch in &group.swatches {
+            blocks.push((COLOR_ENTRY, swatch_block(swatch)));
+        }
+        if named {
+            blocks.push((GROUP_END, vec![]));
+        }
+    }
+
+    let mut bytes = SIGNATURE.to_vec();
+    bytes.extend(1u16.to_be_bytes());
+    bytes.extend(0u16.to_be_bytes());
+    bytes.extend((blocks.len() as u32).to_be_bytes());
+    for (block_type, block) in blocks {
+        bytes.extend(block_type.to_be_bytes());
+        bytes.extend((block.len() as u32).to_be_bytes());
+        bytes.extend(block);
+    }
+
+    bytes
+}
+
+fn swatch_block(swatch: &AseSwatch) -> Vec<u8> {
+    let mut block = vec![];
+    write_name(&mut block, &swatch.name);
+
+    let (model, channels): (&[u8; 4], Vec<f32>) = match swatch.color {
+        AseColor::Rgb(channels) => (b"RGB ", channels.to_vec()),
+        AseColor::Lab(channels) => (b"LAB ", channels.to_vec()),
+        AseColor::Cmyk(channels) => (b"CMYK", channels.to_vec()),
+        AseColor::Gray(gray) => (b"Gray", vec![gray]),
+    };
+    block.extend(model);
+    for channel in channels {
+        block.extend(channel.to_be_bytes());
+    }
+
+    let color_type: u16 = match swatch.color_type {
+        AseColorType::Global => 0,
+        AseColorType::Spot => 1,
+        AseColorType::Normal => 2,
+    };
+    block.extend(color_type.to_be_bytes());
+
+    block
+}
+
+// write_name writes name as a null terminated UTF-16 string prefixed by its length in code units
+fn write_name(block: &mut Vec<u8>, name: &str) {
+    let units: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+    block.extend((units.len() as u16).to_be_bytes());
+    for unit in units {
+        block.extend(unit.to_be_bytes());
+    }
+}
+
+// read_ase decodes the groups of an ASE file, blocks of unknown types are skipped
+pub fn read_ase(bytes: &[u8]) -> anyhow::Result<Vec<AseGroup>> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(4)? != SIGNATURE {
+        bail!("not an ASE file");
+    }
+    let major_version = reader.u16()?;
+    reader.u16()?;
+    if major_version != 1 {
+        bail!("ASE version {} isn't supported", major_version);
+    }
+
+    let block_count = reader.u32()?;
+    let mut groups: Vec<AseGroup> = vec![];
+    let mut group: Option<AseGroup> = None;
+    for i in 0..block_count {
+        let block_type = reader.u16()?;
+        let length = reader.u32()? as usize;
+        let mut block = Reader {
+            bytes: reader.take(length)?,
+            pos: 0,
+        };
+
+        match block_type {
+            GROUP_START => {
+                groups.extend(group.take());
+                group = Some(AseGroup {
+                    name: block.name()?,
+                    swatches: vec![],
+                });
+            }
+            GROUP_END => groups.extend(group.take()),
+            COLOR_ENTRY => {
+                let swatch = block
+                    .swatch()
+                    .with_context(|| format!("failed to read swatch {}", i))?;
+                match &mut group {
+                    Some(group) => group.swatches.push(swatch),
+                    None => match groups.last_mut().filter(|last| last.name.is_empty()) {
+                        Some(ungrouped) => ungrouped.swatches.push(swatch),
+                        None => groups.push(AseGroup {
+                            name: String::new(),
+                            swatches: vec![swatch],
+                        }),
+                    },
+                }
+            }
+            _ => (),
+        }
+    }
+    groups.extend(group);
+
+    Ok(groups)
+}
+
+// import_ase reads the ASE file at path, converting each swatch to RGBA for previewing
+pub fn import_ase(
+    path: &Path,
+    registry: &ColorSpaceRegistry,
+) -> anyhow::Result<Vec<ImportedGroup>> {
+    let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let groups = read_ase(&bytes).with_context(|| format!("failed to import {}", path.display()))?;
+
+    groups
+        .into_iter()
+        .map(|group| {
+            let swatches = group
+                .swatches
+                .into_iter()
+                .map(|swatch| {
+                    let preview = swatch
+                        .color
+                        .to_rgba(registry)
+                        .with_context(|| format!("failed to convert swatch `{}`", swatch.name))?;
+                    Ok(ImportedSwatch {
+                        name: swatch.name,
+                        color: swatch.color,
+                        preview,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?;
+            Ok(ImportedGroup {
+                name: group.name,
+                swatches,
+            })
+        })
+        .collect()
+}
+
+// Reader reads big endian values from the front of bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let Some(taken) = self.bytes.get(self.pos..self.pos + len) else {
+            bail!("unexpected end of file");
+        };
+        self.pos += len;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_be_bytes(self.array()?))
+    }
+
+    fn name(&mut self) -> anyhow::Result<String> {
+        let len = self.u16()? as usize;
+        let units: Vec<u16> = (0..len).map(|_| self.u16()).collect::<anyhow::Result<_>>()?;
+        let name = String::from_utf16(&units).context("swatch name isn't valid UTF-16")?;
+        Ok(name.trim_end_matches('\0').to_string())
+    }
+
+    fn swatch(&mut self) -> anyhow::Result<AseSwatch> {
+        let name = self.name()?;
+        let model = self.array::<4>()?;
+        let color = match &model {
+            b"RGB " => AseColor::Rgb([self.f32()?, self.f32()?, self.f32()?]),
+            b"LAB " => AseColor::Lab([self.f32()?, self.f32()?, self.f32()?]),
+            b"CMYK" => AseColor::Cmyk([self.f32()?, self.f32()?, self.f32()?, self.f32()?]),
+            b"Gray" => AseColor::Gray(self.f32()?),
+            _ => bail!(
+                "swatch `{}` has unknown color model `{}`",
+                name,
+                String::from_utf8_lossy(&model)
+            ),
+        };
+        let color_type = match self.u16()? {
+            0 => AseColorType::Global,
+            1 => AseColorType::Spot,
+            _ => AseColorType::Normal,
+        };
+
+        Ok(AseSwatch {
+            name,
+            color,
+            color_type,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swatch(name: &str, color: AseColor, color_type: AseColorType) -> AseSwatch {
+        AseSwatch {
+            name: name.to_string(),
+            color,
+            color_type,
+        }
+    }
+
+    fn sample_groups() -> Vec<AseGroup> {
+        vec![
+            AseGroup {
+                name: "brand ramp".to_string(),
+                swatches: vec![
+                    swatch("red", AseColor::Rgb([1.0, 0.0, 0.0]), AseColorType::Global),
+                    swatch("lab", AseColor::Lab([0.5, 20.0, -30.0]), AseColorType::Spot),
+                    swatch("cmyk", AseColor::Cmyk([0.1, 0.2, 0.3, 0.4]), AseColorType::Normal),
+                    swatch("grijs ü", AseColor::Gray(0.5), AseColorType::Normal),
+                ],
+            },
+            AseGroup {
+                name: String::new(),
+                swatches: vec![swatch(
+                    "loose",
+                    AseColor::Rgb([0.0, 0.5, 1.0]),
+                    AseColorType::Normal,
+                )],
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trips_every_color_model() {
+        let groups = sample_groups();
+        assert_eq!(read_ase(&write_ase(&groups)).unwrap(), groups);
+    }
+
+    #[test]
+    fn reads_an_ungrouped_swatch_after_a_group() {
+        let bytes = write_ase(&sample_groups());
+        let groups = read_ase(&bytes).unwrap();
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].swatches.len(), 4);
+        assert_eq!(groups[1].name, "");
+        assert_eq!(groups[1].swatches[0].name, "loose");
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = write_ase(&sample_groups());
+        for len in 0..bytes.len() {
+            assert!(read_ase(&bytes[..len]).is_err(), "read {} of {} bytes", len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn writes_lab_palettes_as_lab() {
+        let inputs = |values: [(&str, f32); 3]| {
+            values
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect::<HashMap<String, f32>>()
+        };
+        let palette = |model: &str, inputs: HashMap<String, f32>| Palette {
+            colors: vec![RgbaColor::TRANSPARENT],
+            columns: 1,
+            rows: 1,
+            model: model.to_string(),
+            inputs: vec![inputs],
+            ..Default::default()
+        };
+        let color = |palette: &Palette| palette_to_ase(palette, "p")[0].swatches[0].color;
+
+        let lab = palette("lab", inputs([("l", 50.0), ("a", 20.0), ("b", -30.0)]));
+        assert_eq!(color(&lab), AseColor::Lab([0.5, 20.0, -30.0]));
+
+        let lch = palette("lch", inputs([("l", 50.0), ("c", 10.0), ("h", 90.0)]));
+        let AseColor::Lab([l, a, b]) = color(&lch) else {
+            panic!("expected a Lab swatch");
+        };
+        assert_eq!(l, 0.5);
+        assert!(a.abs() < 1e-4 && (b - 10.0).abs() < 1e-4);
+
+        let hsv = palette("hsv", inputs([("h", 0.0), ("s", 0.0), ("v", 0.0)]));
+        assert_eq!(color(&hsv), AseColor::Rgb([0.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(read_ase(b"GIMP Palette\n").is_err());
+    }
+}
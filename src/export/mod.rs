@@ -0,0 +1,152 @@
+pub mod ase;
+pub mod text;
+pub mod texture;
+pub mod tokens;
+
+use super::expr::parse::Palette;
+use texture::ImageLayout;
+use tokens::RampNaming;
+use anyhow::{bail, Context as _};
+use std::path::Path;
+
+// ExportFormat is a file format the rendered palette can be written as
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Gpl,
+    PaintNet,
+    Hex,
+    Ase,
+    Css,
+    Scss,
+    Tailwind,
+    Dtcg,
+    Png,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 9] = [
+        ExportFormat::Gpl,
+        ExportFormat::PaintNet,
+        ExportFormat::Hex,
+        ExportFormat::Ase,
+        ExportFormat::Css,
+        ExportFormat::Scss,
+        ExportFormat::Tailwind,
+        ExportFormat::Dtcg,
+        ExportFormat::Png,
+    ];
+
+    // extension returns the file extension the format is chosen by
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Gpl => "gpl",
+            ExportFormat::PaintNet => "txt",
+            ExportFormat::Hex => "hex",
+            ExportFormat::Ase => "ase",
+            ExportFormat::Css => "css",
+            ExportFormat::Scss => "scss",
+            ExportFormat::Tailwind => "js",
+            ExportFormat::Dtcg => "json",
+            ExportFormat::Png => "png",
+        }
+    }
+
+    // from_path picks the format of path by its extension
+    pub fn from_path(path: &Path) -> anyhow::Result<ExportFormat> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match ExportFormat::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+        {
+            Some(format) => Ok(format),
+            None => {
+                let extensions: Vec<&str> =
+                    ExportFormat::ALL.iter().map(|format| format.extension()).collect();
+                bail!(
+                    "can't export to `.{}`, expected one of .{}",
+                    extension,
+                    extensions.join(", .")
+                )
+            }
+        }
+    }
+}
+
+// ExportOptions are the settings of the formats that can't be derived from the palette
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    // namings names the design tokens of each ramp, ramps without one get the default naming
+    pub namings: Vec<RampNaming>,
+
+    pub image_layout: ImageLayout,
+}
+
+// export_palette writes palette to path in the format picked by its extension. The stem of path
+// names the palette and its swatches, everything else is set by options.
+pub fn export_palette(
+    path: &Path,
+    palette: &Palette,
+    options: &ExportOptions,
+) -> anyhow::Result<()> {
+    let format = ExportFormat::from_path(path)?;
+    ensure_rendered(palette)?;
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("palette");
+
+    let namings = &options.namings;
+    let bytes = match format {
+        ExportFormat::Gpl => text::to_gpl(palette, name).into_bytes(),
+        ExportFormat::PaintNet => text::to_paint_net(palette).into_bytes(),
+        ExportFormat::Hex => text::to_hex(palette).into_bytes(),
+        ExportFormat::Ase => ase::write_ase(&ase::palette_to_ase(palette, name)),
+        ExportFormat::Css => tokens::to_css(&tokens::ramps(palette, namings)?).into_bytes(),
+        ExportFormat::Scss => tokens::to_scss(&tokens::ramps(palette, namings)?).into_bytes(),
+        ExportFormat::Tailwind => {
+            tokens::to_tailwind(&tokens::ramps(palette, namings)?)?.into_bytes()
+        }
+        ExportFormat::Dtcg => tokens::to_dtcg(&tokens::ramps(palette, namings)?).into_bytes(),
+        ExportFormat::Png => {
+            let image = texture::palette_image(palette, options.image_layout)?;
+            return texture::save_png(image, path);
+        }
+    };
+    std::fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
+}
+
+// ensure_rendered fails if palette or any of its colors failed to render, rather than exporting
+// the transparent placeholder left in place of a color
+pub fn ensure_rendered(palette: &Palette) -> anyhow::Result<()> {
+    if let Some(failure) = &palette.failure {
+        bail!("the palette failed to render: {}", failure);
+    }
+    if let Some((_, err)) = palette.errors.first() {
+        bail!(
+            "{} of {} colors failed to render: {:#}",
+            palette.errors.len(),
+            palette.colors.len(),
+            err
+        );
+    }
+    if palette.colors.is_empty() {
+        bail!("the palette has no colors");
+    }
+
+    Ok(())
+}
+
+// swatch_name names color index of palette after the palette, by column for a single row and by
+// row and column for a grid
+pub fn swatch_name(palette: &Palette, name: &str, index: usize) -> String {
+    let columns = palette.columns.max(1) as usize;
+    if palette.rows > 1 {
+        format!("{} {}-{}", name, index / columns, index % columns)
+    } else {
+        format!("{} {}", name, index)
+    }
+}
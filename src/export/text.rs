@@ -0,0 +1,48 @@
+use super::super::expr::parse::Palette;
+use super::swatch_name;
+
+// to_gpl writes palette as a GIMP palette, which Krita and Aseprite read as well. GIMP palettes
+// have no alpha so it's dropped.
+pub fn to_gpl(palette: &Palette, name: &str) -> String {
+    let mut text = format!(
+        "GIMP Palette\nName: {}\nColumns: {}\n#\n",
+        name, palette.columns
+    );
+    for (i, color) in palette.colors.iter().enumerate() {
+        let [red, green, blue, _] = color.to_u8();
+        text += &format!(
+            "{:>3} {:>3} {:>3}\t{}\n",
+            red,
+            green,
+            blue,
+            swatch_name(palette, name, i)
+        );
+    }
+
+    text
+}
+
+// to_paint_net writes palette as a Paint.NET palette of AARRGGBB colors. Paint.NET itself only
+// reads the first 96 colors.
+pub fn to_paint_net(palette: &Palette) -> String {
+    let mut text = String::from(
+        "; paint.net Palette File\n\
+         ; Lines that start with a semicolon are comments\n\
+         ; Colors are written as 8-digit hexadecimal numbers: aarrggbb\n",
+    );
+    for color in &palette.colors {
+        let [red, green, blue, alpha] = color.to_u8();
+        text += &format!("{:02X}{:02X}{:02X}{:02X}\n", alpha, red, green, blue);
+    }
+
+    text
+}
+
+// to_hex writes palette as a list of rrggbb colors, one per line, as read by Lospec and Aseprite
+pub fn to_hex(palette: &Palette) -> String {
+    palette
+        .colors
+        .iter()
+        .map(|color| format!("{}\n", color.to_hex().trim_start_matches('#')))
+        .collect()
+}
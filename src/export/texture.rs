@@ -0,0 +1,132 @@
+use super::super::colorgen::color::RgbaColor;
+use super::super::expr::parse::Palette;
+use anyhow::{bail, Context as _};
+use bevy::prelude::Image;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use palette::{LinSrgba, Mix, Srgba};
+use std::path::Path;
+
+// ImageLayout is how the palette is laid out in an exported image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageLayout {
+    // Strip places every color side by side in a single row, each as a square swatch of the given
+    // size in pixels
+    Strip(u32),
+
+    // Lut interpolates each row of the palette into a gradient of the given width, one pixel high
+    // per row
+    Lut(u32),
+
+    // Grid lays the colors out in the rows and columns of the palette, each as a square swatch of
+    // the given size in pixels
+    Grid(u32),
+}
+
+impl Default for ImageLayout {
+    fn default() -> ImageLayout {
+        ImageLayout::Strip(1)
+    }
+}
+
+// MAX_IMAGE_SIDE and MAX_IMAGE_PIXELS bound exported images, so that a large swatch size or
+// color count fails instead of allocating gigabytes
+pub const MAX_IMAGE_SIDE: u32 = 16384;
+pub const MAX_IMAGE_PIXELS: u64 = 64 * 1024 * 1024;
+
+// palette_image renders palette into an sRGB image laid out by layout
+pub fn palette_image(palette: &Palette, layout: ImageLayout) -> anyhow::Result<Image> {
+    let columns = palette.columns.max(1);
+    let (width, height) = image_size(palette, layout)?;
+    let pixels: Vec<RgbaColor> = match layout {
+        ImageLayout::Strip(size) => {
+            let row: Vec<RgbaColor> = (0..width)
+                .map(|px| palette.colors[(px / size) as usize])
+                .collect();
+            row.repeat(size as usize)
+        }
+        ImageLayout::Lut(width) => palette
+            .rows_iter()
+            .flat_map(|row| (0..width).map(move |px| gradient_at(row, px, width)))
+            .collect(),
+        ImageLayout::Grid(size) => (0..height)
+            .flat_map(|py| (0..width).map(move |px| (px / size, py / size)))
+            .map(|(x, y)| palette.colors[(y * columns + x) as usize])
+            .collect(),
+    };
+
+    Ok(Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixels.into_iter().flat_map(|color| color.to_u8()).collect(),
+        TextureFormat::Rgba8UnormSrgb,
+    ))
+}
+
+// image_size returns the width and height of the image palette_image renders, failing before
+// anything is allocated if the layout is invalid or the image would be too large
+fn image_size(palette: &Palette, layout: ImageLayout) -> anyhow::Result<(u32, u32)> {
+    let columns = palette.columns.max(1);
+    let color_count = u32::try_from(palette.colors.len()).ok();
+    let size = match layout {
+        ImageLayout::Strip(size) | ImageLayout::Grid(size) if size == 0 => {
+            bail!("swatches must be at least a pixel wide")
+        }
+        ImageLayout::Lut(width) if width < 2 => {
+            bail!("a gradient must be at least 2 pixels wide")
+        }
+        ImageLayout::Strip(size) => color_count
+            .and_then(|count| count.checked_mul(size))
+            .map(|width| (width, size)),
+        ImageLayout::Lut(width) => Some((width, palette.rows)),
+        ImageLayout::Grid(size) => columns
+            .checked_mul(size)
+            .zip(palette.rows.checked_mul(size)),
+    };
+
+    match size {
+        Some((width, height))
+            if width <= MAX_IMAGE_SIDE
+                && height <= MAX_IMAGE_SIDE
+                && width as u64 * height as u64 <= MAX_IMAGE_PIXELS =>
+        {
+            Ok((width, height))
+        }
+        _ => bail!(
+            "the image would be larger than {} pixels a side or {} pixels in total",
+            MAX_IMAGE_SIDE,
+            MAX_IMAGE_PIXELS
+        ),
+    }
+}
+
+// gradient_at samples pixel px of a gradient width pixels wide running through the colors of row
+// at even spacing. Colors are mixed in linear light, as a GPU filters an sRGB texture.
+fn gradient_at(row: &[RgbaColor], px: u32, width: u32) -> RgbaColor {
+    let position = px as f32 / (width - 1) as f32 * (row.len() - 1) as f32;
+    let i = (position.floor() as usize).min(row.len().saturating_sub(2));
+    let (a, b) = (row[i], row[(i + 1).min(row.len() - 1)]);
+
+    let linear = |color: RgbaColor| -> LinSrgba {
+        Srgba::new(color.red, color.green, color.blue, color.alpha).into_linear()
+    };
+    let mixed = Srgba::from_linear(linear(a).mix(linear(b), position - i as f32));
+    RgbaColor {
+        red: mixed.red,
+        green: mixed.green,
+        blue: mixed.blue,
+        alpha: mixed.alpha,
+    }
+}
+
+// save_png writes image to path as a PNG
+pub fn save_png(image: Image, path: &Path) -> anyhow::Result<()> {
+    image
+        .try_into_dynamic()
+        .context("failed to convert the palette image")?
+        .save(path)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
@@ -0,0 +1,221 @@
+use super::super::colorgen::color::RgbaColor;
+use super::super::expr::parse::Palette;
+use anyhow::{anyhow, bail};
+use std::collections::HashSet;
+
+// TAILWIND_STEPS are the keys of a Tailwind color scale, from lightest to darkest
+pub const TAILWIND_STEPS: [u32; 11] = [50, 100, 200, 300, 400, 500, 600, 700, 800, 900, 950];
+
+// RampNaming names the tokens of one ramp, a row of the palette. key is a template for the key of
+// each color within the ramp, where `{n}` is replaced by the column of the color and `{step}` by
+// its Tailwind step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RampNaming {
+    pub name: String,
+    pub key: String,
+}
+
+impl RampNaming {
+    // default_for names ramp y of a palette with row_count ramps, so that a single ramp exports
+    // as `--ramp-0`, `--ramp-1` and so on
+    pub fn default_for(y: usize, row_count: usize) -> RampNaming {
+        RampNaming {
+            name: if row_count > 1 {
+                format!("ramp-{}", y)
+            } else {
+                "ramp".to_string()
+            },
+            key: "{n}".to_string(),
+        }
+    }
+}
+
+// Ramp is a named ramp of the palette with the key of each of its colors
+pub struct Ramp {
+    pub name: String,
+    pub colors: Vec<(String, RgbaColor)>,
+}
+
+// ramps names each row of palette with the naming of the same index, rows past the end of
+// namings are given the default naming
+pub fn ramps(palette: &Palette, namings: &[RampNaming]) -> anyhow::Result<Vec<Ramp>> {
+    let mut names: HashSet<String> = HashSet::new();
+    palette
+        .rows_iter()
+        .enumerate()
+        .map(|(y, row)| {
+            let naming = namings
+                .get(y)
+                .cloned()
+                .unwrap_or_else(|| RampNaming::default_for(y, palette.rows as usize));
+            check_identifier(&naming.name, true).map_err(|problem| {
+                anyhow!("ramp name `{}` can't be used in CSS: {}", naming.name, problem)
+            })?;
+            if !names.insert(naming.name.clone()) {
+                bail!("more than one ramp is named `{}`", naming.name);
+            }
+
+            let mut keys: HashSet<String> = HashSet::new();
+            let colors = row
+                .iter()
+                .enumerate()
+                .map(|(x, color)| {
+                    let key = expand_key(&naming.key, x, row.len())?;
+                    check_identifier(&key, false).map_err(|problem| {
+                        anyhow!(
+                            "ramp `{}` key `{}` can't be used in CSS: {}",
+                            naming.name,
+                            key,
+                            problem
+                        )
+                    })?;
+                    if !keys.insert(key.clone()) {
+                        bail!("ramp `{}` names more than one color `{}`", naming.name, key);
+                    }
+                    Ok((key, *color))
+                })
+                .collect::<anyhow::Result<_>>()?;
+
+            Ok(Ramp {
+                name: naming.name,
+                colors,
+            })
+        })
+        .collect()
+}
+
+// check_identifier checks that ident can be written unescaped in a CSS custom property or SCSS
+// variable name. Keys follow the ramp name, so only names are kept from starting with a digit.
+fn check_identifier(ident: &str, is_name: bool) -> Result<(), String> {
+    if ident.is_empty() {
+        return Err("it is empty".to_string());
+    }
+    let start = ident.strip_prefix('-').unwrap_or(ident).chars().next();
+    if is_name && !start.is_some_and(|c| c.is_alphabetic() || c == '_' || !c.is_ascii()) {
+        return Err("names must start with a letter or `_`, optionally after a `-`".to_string());
+    }
+    match ident
+        .chars()
+        .find(|&c| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()))
+    {
+        Some(c) => Err(format!("{:?} isn't allowed, use letters, digits, `-` or `_`", c)),
+        None => Ok(()),
+    }
+}
+
+// expand_key fills in the placeholders of template for column x of a ramp of len colors
+fn expand_key(template: &str, x: usize, len: usize) -> anyhow::Result<String> {
+    let mut key = template.replace("{n}", &x.to_string());
+    if key.contains("{step}") {
+        key = key.replace("{step}", &tailwind_step(x, len)?.to_string());
+    }
+
+    Ok(key)
+}
+
+// tailwind_step returns the Tailwind key of column x, which needs a ramp of exactly 11 colors
+fn tailwind_step(x: usize, len: usize) -> anyhow::Result<u32> {
+    if len != TAILWIND_STEPS.len() {
+        bail!(
+            "Tailwind steps need ramps of {} colors but the palette has {}",
+            TAILWIND_STEPS.len(),
+            len
+        );
+    }
+
+    Ok(TAILWIND_STEPS[x])
+}
+
+// css_color formats color as #rrggbb, or #rrggbbaa when it isn't opaque
+pub fn css_color(color: RgbaColor) -> String {
+    if color.to_u8()[3] == u8::MAX {
+        color.to_hex()
+    } else {
+        color.to_hex_alpha()
+    }
+}
+
+// to_css writes each color as a custom property named `--<ramp>-<key>` on :root
+pub fn to_css(ramps: &[Ramp]) -> String {
+    let mut text = String::from(":root {\n");
+    for ramp in ramps {
+        for (key, color) in &ramp.colors {
+            text += &format!("  --{}-{}: {};\n", ramp.name, key, css_color(*color));
+        }
+    }
+    text += "}\n";
+
+    text
+}
+
+// to_scss writes each ramp as a SCSS map from key to color
+pub fn to_scss(ramps: &[Ramp]) -> String {
+    let mut text = String::new();
+    for ramp in ramps {
+        text += &format!("${}: (\n", ramp.name);
+        for (key, color) in &ramp.colors {
+            text += &format!("  \"{}\": {},\n", key, css_color(*color));
+        }
+        text += ");\n";
+    }
+
+    text
+}
+
+// to_tailwind writes the ramps as the `colors` of a Tailwind theme, keyed by Tailwind step rather
+// than by each ramp's key template
+pub fn to_tailwind(ramps: &[Ramp]) -> anyhow::Result<String> {
+    let mut text = String::from("module.exports = {\n  colors: {\n");
+    for ramp in ramps {
+        text += &format!("    {}: {{\n", json_string(&ramp.name));
+        for (x, (_, color)) in ramp.colors.iter().enumerate() {
+            let step = tailwind_step(x, ramp.colors.len())?;
+            text += &format!("      {}: {},\n", step, json_string(&css_color(*color)));
+        }
+        text += "    },\n";
+    }
+    text += "  },\n};\n";
+
+    Ok(text)
+}
+
+// to_dtcg writes the ramps as W3C Design Tokens Community Group JSON, a group per ramp holding a
+// color token per key
+pub fn to_dtcg(ramps: &[Ramp]) -> String {
+    let groups: Vec<String> = ramps
+        .iter()
+        .map(|ramp| {
+            let tokens: Vec<String> = ramp
+                .colors
+                .iter()
+                .map(|(key, color)| {
+                    format!(
+                        "    {}: {{\n      \"$type\": \"color\",\n      \"$value\": \"{}\"\n    }}",
+                        json_string(key),
+                        css_color(*color)
+                    )
+                })
+                .collect();
+            format!("  {}: {{\n{}\n  }}", json_string(&ramp.name), tokens.join(",\n"))
+        })
+        .collect();
+
+    format!("{{\n{}\n}}\n", groups.join(",\n"))
+}
+
+// json_string quotes s as a JSON string, which is also a valid JavaScript string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            c if c.is_control() => quoted += &format!("\\u{:04x}", c as u32),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
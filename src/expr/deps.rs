@@ -0,0 +1,126 @@
+use super::parse::ExprRow;
+use anyhow::bail;
+use std::collections::HashMap;
+
+// VisitState tracks how far a row has made it through the depth-first ordering
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    Unvisited,
+    InProgress,
+    Done,
+}
+
+// eval_order returns the indices of rows ordered so that each row comes after every row it
+// references, failing with the names of the offending rows if any of them reference each other in
+// a cycle. Rows are compiled through their cache, hence the mutable borrow.
+pub fn eval_order(rows: &mut [ExprRow]) -> anyhow::Result<Vec<usize>> {
+    // row_indices maps each assigned variable to the row that assigns it
+    let mut row_indices: HashMap<String, usize> = HashMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        if row.var.is_empty() {
+            continue;
+        }
+        if row_indices.insert(row.var.clone(), i).is_some() {
+            bail!("row `{}` is defined more than once", row.var);
+        }
+    }
+
+    // dependencies holds the indices of the rows read by each row, rows that fail to parse are
+    // left without any so that the parse error is reported when they are evaluated
+    let mut dependencies: Vec<Vec<usize>> = Vec::with_capacity(rows.len());
+    for row in rows.iter_mut() {
+        let mut row_dependencies: Vec<usize> = match row.compile() {
+            Ok(tree) => tree
+                .iter_read_variable_identifiers()
+                .filter_map(|identifier| row_indices.get(identifier).copied())
+                .collect(),
+            Err(_) => vec![],
+        };
+        row_dependencies.sort_unstable();
+        row_dependencies.dedup();
+        dependencies.push(row_dependencies);
+    }
+
+    let mut states = vec![VisitState::Unvisited; rows.len()];
+    let mut stack: Vec<usize> = vec![];
+    let mut order: Vec<usize> = Vec::with_capacity(rows.len());
+    for i in 0..rows.len() {
+        if states[i] == VisitState::Unvisited {
+            visit(i, rows, &dependencies, &mut states, &mut stack, &mut order)?;
+        }
+    }
+
+    Ok(order)
+}
+
+// visit orders the dependencies of row i before appending it to order, stack holds the chain of
+// rows currently being visited so that a cycle can be reported in full
+fn visit(
+    i: usize,
+    rows: &[ExprRow],
+    dependencies: &[Vec<usize>],
+    states: &mut [VisitState],
+    stack: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> anyhow::Result<()> {
+    states[i] = VisitState::InProgress;
+    stack.push(i);
+
+    for &dependency in &dependencies[i] {
+        match states[dependency] {
+            VisitState::Unvisited => visit(dependency, rows, dependencies, states, stack, order)?,
+            VisitState::InProgress => {
+                let cycle_start = stack.iter().position(|&j| j == dependency).unwrap_or(0);
+                let cycle: Vec<String> = stack[cycle_start..]
+                    .iter()
+                    .chain(std::iter::once(&dependency))
+                    .map(|&j| format!("`{}`", rows[j].var))
+                    .collect();
+                bail!("rows form a dependency cycle: {}", cycle.join(" -> "));
+            }
+            VisitState::Done => (),
+        }
+    }
+
+    stack.pop();
+    states[i] = VisitState::Done;
+    order.push(i);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[(&str, &str)]) -> Vec<ExprRow> {
+        rows.iter().map(|(var, expr)| ExprRow::new(var, expr)).collect()
+    }
+
+    #[test]
+    fn orders_rows_after_the_rows_they_read() {
+        let mut rows = rows(&[("c", "b + a"), ("b", "a * 2"), ("a", "t"), ("", "")]);
+        assert_eq!(eval_order(&mut rows).unwrap(), vec![2, 1, 0, 3]);
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let mut rows = rows(&[("a", "b + 1"), ("b", "a + 1")]);
+        let err = eval_order(&mut rows).unwrap_err();
+        assert_eq!(err.to_string(), "rows form a dependency cycle: `a` -> `b` -> `a`");
+    }
+
+    #[test]
+    fn reports_self_references() {
+        let mut rows = rows(&[("a", "a + 1")]);
+        let err = eval_order(&mut rows).unwrap_err();
+        assert_eq!(err.to_string(), "rows form a dependency cycle: `a` -> `a`");
+    }
+
+    #[test]
+    fn reports_duplicate_rows() {
+        let mut rows = rows(&[("a", "1"), ("a", "2")]);
+        let err = eval_order(&mut rows).unwrap_err();
+        assert_eq!(err.to_string(), "row `a` is defined more than once");
+    }
+}
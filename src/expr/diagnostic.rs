@@ -0,0 +1,176 @@
+use evalexpr::{EvalexprError, Value};
+use std::ops::Range;
+
+// DiagnosticKind is the category of problem a Diagnostic reports
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiagnosticKind {
+    Parse,
+    UnknownVariable,
+    UnknownFunction,
+    Type,
+    DivisionByZero,
+    NonFinite,
+    OutOfRange,
+    Other,
+}
+
+// Diagnostic explains why a row's expression failed, and where in the expression it failed
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+
+    // span is the byte range of the expression the diagnostic points at
+    pub span: Range<usize>,
+
+    // index is the color index evaluation failed at, parse errors fail before any index
+    pub index: Option<u32>,
+}
+
+impl Diagnostic {
+    // parse builds the diagnostic for an expression that failed to parse
+    pub fn parse(expr: &str, err: &EvalexprError) -> Diagnostic {
+        let span = match err {
+            EvalexprError::UnmatchedLBrace => unmatched_brace(expr, true),
+            EvalexprError::UnmatchedRBrace => unmatched_brace(expr, false),
+            EvalexprError::UnmatchedDoubleQuote => expr.rfind('"').map(|i| i..i + 1),
+            _ => None,
+        };
+
+        Diagnostic {
+            kind: DiagnosticKind::Parse,
+            message: err.to_string(),
+            span: span.unwrap_or_else(|| whole(expr)),
+            index: None,
+        }
+    }
+
+    // eval builds the diagnostic for an expression that failed to evaluate at color index
+    pub fn eval(expr: &str, err: &EvalexprError, index: u32) -> Diagnostic {
+        let (kind, span) = match err {
+            EvalexprError::VariableIdentifierNotFound(identifier) => {
+                (DiagnosticKind::UnknownVariable, identifier_span(expr, identifier))
+            }
+            EvalexprError::FunctionIdentifierNotFound(identifier) => {
+                (DiagnosticKind::UnknownFunction, identifier_span(expr, identifier))
+            }
+            EvalexprError::DivisionError { divisor, .. }
+            | EvalexprError::ModulationError { divisor, .. }
+                if matches!(divisor, Value::Int(0)) =>
+            {
+                (DiagnosticKind::DivisionByZero, None)
+            }
+            EvalexprError::ExpectedString { .. }
+            | EvalexprError::ExpectedInt { .. }
+            | EvalexprError::ExpectedFloat { .. }
+            | EvalexprError::ExpectedNumber { .. }
+            | EvalexprError::ExpectedNumberOrString { .. }
+            | EvalexprError::ExpectedBoolean { .. }
+            | EvalexprError::ExpectedTuple { .. }
+            | EvalexprError::ExpectedFixedLengthTuple { .. }
+            | EvalexprError::ExpectedRangedLengthTuple { .. }
+            | EvalexprError::ExpectedEmpty { .. }
+            | EvalexprError::TypeError { .. }
+            | EvalexprError::WrongTypeCombination { .. }
+            | EvalexprError::WrongFunctionArgumentAmount { .. }
+            | EvalexprError::WrongOperatorArgumentAmount { .. } => (DiagnosticKind::Type, None),
+            _ => (DiagnosticKind::Other, None),
+        };
+
+        Diagnostic {
+            kind,
+            message: err.to_string(),
+            span: span.unwrap_or_else(|| whole(expr)),
+            index: Some(index),
+        }
+    }
+
+    // non_finite builds the diagnostic for an expression that evaluated to NaN or infinity
+    pub fn non_finite(expr: &str, value: f64, index: u32) -> Diagnostic {
+        Diagnostic {
+            kind: DiagnosticKind::NonFinite,
+            message: format!("expression evaluated to {}", value),
+            span: whole(expr),
+            index: Some(index),
+        }
+    }
+
+    // not_a_number builds the diagnostic for a model input that evaluated to a non-numeric type
+    pub fn not_a_number(expr: &str, type_name: &str, index: u32) -> Diagnostic {
+        Diagnostic {
+            kind: DiagnosticKind::Type,
+            message: format!("expected a number but the expression evaluated to {}", type_name),
+            span: whole(expr),
+            index: Some(index),
+        }
+    }
+
+    // wrong_shape builds the diagnostic for a tuple with the wrong number of values, or a value
+    // that should have been a tuple
+    pub fn wrong_shape(expr: &str, message: &str, index: u32) -> Diagnostic {
+        Diagnostic {
+            kind: DiagnosticKind::Type,
+            message: message.to_string(),
+            span: whole(expr),
+            index: Some(index),
+        }
+    }
+
+    // out_of_range builds the diagnostic for a model input whose value fell outside of its range
+    // under the Error policy
+    pub fn out_of_range(expr: &str, message: &str, index: u32) -> Diagnostic {
+        Diagnostic {
+            kind: DiagnosticKind::OutOfRange,
+            message: message.to_string(),
+            span: whole(expr),
+            index: Some(index),
+        }
+    }
+
+    // char_span converts span into a range of chars of expr, for fields indexed by char such as
+    // a Rope. expr may have been edited since the diagnostic was made, so a byte offset that
+    // falls inside a char or past the end is counted up to the next char instead of sliced.
+    pub fn char_span(&self, expr: &str) -> Range<usize> {
+        let char_index = |byte: usize| expr.char_indices().take_while(|(i, _)| *i < byte).count();
+        char_index(self.span.start)..char_index(self.span.end)
+    }
+}
+
+// whole spans the expression without its surrounding whitespace
+fn whole(expr: &str) -> Range<usize> {
+    let start = expr.len() - expr.trim_start().len();
+    let end = expr.trim_end().len().max(start);
+    start..end
+}
+
+// identifier_span finds the first occurrence of identifier in expr that isn't part of a longer
+// identifier
+fn identifier_span(expr: &str, identifier: &str) -> Option<Range<usize>> {
+    let is_identifier_char = |c: char| c.is_alphanumeric() || c == '_' || c == ':';
+    expr.match_indices(identifier)
+        .map(|(start, _)| start..start + identifier.len())
+        .find(|span| {
+            let before = expr[..span.start].chars().next_back();
+            let after = expr[span.end..].chars().next();
+            !before.is_some_and(is_identifier_char) && !after.is_some_and(is_identifier_char)
+        })
+}
+
+// unmatched_brace finds the first opening brace that is never closed, or the first closing brace
+// that was never opened
+fn unmatched_brace(expr: &str, opening: bool) -> Option<Range<usize>> {
+    let mut open: Vec<usize> = vec![];
+    for (i, c) in expr.char_indices() {
+        match c {
+            '(' => open.push(i),
+            ')' => {
+                if open.pop().is_none() && !opening {
+                    return Some(i..i + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    open.first().filter(|_| opening).map(|&i| i..i + 1)
+}
@@ -0,0 +1,151 @@
+use anyhow::{bail, Context as _};
+use evalexpr::{ContextWithMutableVariables, EvalexprResult, HashMapContext};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+// DOMAIN_VARS are the variables supplied by the domain, which user rows may not assign
+pub const DOMAIN_VARS: [&str; 9] = [
+    "x",
+    "n",
+    "t",
+    "count",
+    "i_rev",
+    "y",
+    "tx",
+    "ty",
+    "row_count",
+];
+
+// COLUMN_RANGE, ROW_RANGE and EXPONENT_RANGE bound a domain, so that a palette can be rendered
+// within a frame
+pub const COLUMN_RANGE: RangeInclusive<u32> = 1..=4096;
+pub const ROW_RANGE: RangeInclusive<u32> = 1..=256;
+pub const EXPONENT_RANGE: RangeInclusive<f64> = 0.05..=10.0;
+
+// Spacing decides where along 0..1 each column of the domain places `t`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Spacing {
+    Uniform,
+
+    // Power raises uniform positions to the given exponent, above 1 colors cluster toward the
+    // start of the ramp and below 1 toward the end
+    Power(f64),
+
+    // SmoothStep clusters colors toward both ends of the ramp
+    SmoothStep,
+
+    // Stops gives the position of each column explicitly, so the number of stops is the number of
+    // columns
+    Stops(Vec<f64>),
+}
+
+// Domain describes the colors a palette is rendered for, and the variables each one supplies to
+// the expressions. Colors are laid out in a grid of rows, where a single row is a plain ramp.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Domain {
+    // color_count is the number of columns in each row
+    pub color_count: u32,
+    pub spacing: Spacing,
+
+    // row_count is the number of rows, each sampled uniformly along `ty`
+    pub row_count: u32,
+}
+
+impl Default for Domain {
+    fn default() -> Domain {
+        Domain {
+            color_count: 8,
+            spacing: Spacing::Uniform,
+            row_count: 1,
+        }
+    }
+}
+
+impl Domain {
+    // columns returns the number of colors in each row
+    pub fn columns(&self) -> u32 {
+        match &self.spacing {
+            Spacing::Stops(stops) => stops.len() as u32,
+            _ => self.color_count,
+        }
+    }
+
+    // len returns the number of colors in the domain, failing if there are too many to count
+    pub fn len(&self) -> anyhow::Result<u32> {
+        self.columns()
+            .checked_mul(self.row_count)
+            .context("the domain has too many colors")
+    }
+
+    // validate checks that the number of columns and rows and the spacing exponent are within
+    // the bounds the panel allows, for domains that weren't edited in it
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !COLUMN_RANGE.contains(&self.columns()) {
+            bail!(
+                "the domain has {} colors per row, expected {} to {}",
+                self.columns(),
+                COLUMN_RANGE.start(),
+                COLUMN_RANGE.end()
+            );
+        }
+        if !ROW_RANGE.contains(&self.row_count) {
+            bail!(
+                "the domain has {} rows, expected {} to {}",
+                self.row_count,
+                ROW_RANGE.start(),
+                ROW_RANGE.end()
+            );
+        }
+        if let Spacing::Power(exponent) = self.spacing {
+            if !EXPONENT_RANGE.contains(&exponent) {
+                bail!(
+                    "the spacing exponent is {}, expected {} to {}",
+                    exponent,
+                    EXPONENT_RANGE.start(),
+                    EXPONENT_RANGE.end()
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    // t_at returns the position of column x along 0..1
+    pub fn t_at(&self, x: u32) -> f64 {
+        let uniform = normalize(x, self.columns());
+
+        match &self.spacing {
+            Spacing::Uniform => uniform,
+            Spacing::Power(exponent) => uniform.powf(*exponent),
+            Spacing::SmoothStep => uniform * uniform * (3.0 - 2.0 * uniform),
+            Spacing::Stops(stops) => stops[x as usize],
+        }
+    }
+
+    // set_vars assigns the domain variables for color i into ctx, colors are indexed row by row
+    pub fn set_vars(&self, ctx: &mut HashMapContext, i: u32) -> EvalexprResult<()> {
+        let (x, y) = (i % self.columns(), i / self.columns());
+        let count = self.columns() as f64;
+        ctx.set_value("x".into(), (x as f64).into())?;
+        ctx.set_value("n".into(), count.into())?;
+        ctx.set_value("count".into(), count.into())?;
+        ctx.set_value("t".into(), self.t_at(x).into())?;
+        ctx.set_value("tx".into(), self.t_at(x).into())?;
+        ctx.set_value("i_rev".into(), (count - 1.0 - x as f64).into())?;
+        ctx.set_value("y".into(), (y as f64).into())?;
+        ctx.set_value("ty".into(), normalize(y, self.row_count).into())?;
+        ctx.set_value("row_count".into(), (self.row_count as f64).into())?;
+
+        Ok(())
+    }
+}
+
+// normalize maps i of 0..len onto 0..1, with a lone index placed at 0
+fn normalize(i: u32, len: u32) -> f64 {
+    if len > 1 {
+        i as f64 / (len - 1) as f64
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,170 @@
+use evalexpr::{
+    ContextWithMutableFunctions, EvalexprResult, FloatType, Function, HashMapContext, Value,
+};
+use std::f64::consts::PI;
+
+// register_functions adds the interpolation and easing function library to ctx
+pub fn register_functions(ctx: &mut HashMapContext) -> EvalexprResult<()> {
+    register(ctx, "lerp", |[a, b, t]| lerp(a, b, t))?;
+    register(ctx, "mix", |[a, b, t]| lerp(a, b, t))?;
+    register(ctx, "lerp_hue", |[a, b, t]| lerp_hue(a, b, t))?;
+    // the bounds are ordered rather than passed to f64::clamp, which panics on reversed or NaN
+    // bounds typed into an expression
+    register(ctx, "clamp", |[value, min, max]| value.max(min.min(max)).min(max.max(min)))?;
+    register(ctx, "smoothstep", |[edge0, edge1, value]| {
+        let t = ((value - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    })?;
+    register(ctx, "remap", |[value, in_min, in_max, out_min, out_max]| {
+        lerp(out_min, out_max, (value - in_min) / (in_max - in_min))
+    })?;
+    register(ctx, "bezier", |[p1, p2, t]| bezier(p1, p2, t))?;
+    register(ctx, "cubic_bezier", |[x1, y1, x2, y2, t]| {
+        cubic_bezier(x1, y1, x2, y2, t)
+    })?;
+
+    // a row made of a single param call takes the value of its slider, anywhere else param only
+    // supplies its default
+    register(ctx, "param", |[default, min, max]| default.max(min).min(max.max(min)))?;
+
+    // each easing family is registered as ease_in_*, ease_out_* and ease_in_out_*
+    let easings: [(&str, fn(FloatType) -> FloatType); 8] = [
+        ("quad", |t| t.powi(2)),
+        ("cubic", |t| t.powi(3)),
+        ("quart", |t| t.powi(4)),
+        ("quint", |t| t.powi(5)),
+        ("sine", |t| 1.0 - (t * PI / 2.0).cos()),
+        ("expo", |t| if t <= 0.0 { 0.0 } else { 2f64.powf(10.0 * t - 10.0) }),
+        ("circ", |t| 1.0 - (1.0 - t * t).max(0.0).sqrt()),
+        ("back", |t| 2.70158 * t.powi(3) - 1.70158 * t.powi(2)),
+    ];
+    for (family, ease_in) in easings {
+        register(ctx, &format!("ease_in_{}", family), move |[t]| ease_in(t))?;
+        register(ctx, &format!("ease_out_{}", family), move |[t]| {
+            1.0 - ease_in(1.0 - t)
+        })?;
+        register(ctx, &format!("ease_in_out_{}", family), move |[t]| {
+            if t < 0.5 {
+                ease_in(2.0 * t) / 2.0
+            } else {
+                1.0 - ease_in(2.0 - 2.0 * t) / 2.0
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+// register adds a function of N numeric arguments to ctx, ints are accepted in place of floats
+pub fn register<const N: usize>(
+    ctx: &mut HashMapContext,
+    name: &str,
+    function: impl Fn([FloatType; N]) -> FloatType + Clone + Send + Sync + 'static,
+) -> EvalexprResult<()> {
+    ctx.set_function(
+        name.to_string(),
+        Function::new(move |argument| Ok(Value::Float(function(float_args(argument)?)))),
+    )
+}
+
+// float_args unpacks the argument of a function call into N numbers, a single argument is passed
+// bare while several are passed as a tuple
+fn float_args<const N: usize>(argument: &Value) -> EvalexprResult<[FloatType; N]> {
+    let mut args = [0.0; N];
+    if N == 1 {
+        args[0] = argument.as_number()?;
+    } else {
+        for (arg, value) in args.iter_mut().zip(argument.as_fixed_len_tuple(N)?) {
+            *arg = value.as_number()?;
+        }
+    }
+
+    Ok(args)
+}
+
+fn lerp(a: FloatType, b: FloatType, t: FloatType) -> FloatType {
+    a + (b - a) * t
+}
+
+// lerp_hue interpolates between two hues in degrees along the shorter way around the circle,
+// returning a hue in 0..360
+fn lerp_hue(a: FloatType, b: FloatType, t: FloatType) -> FloatType {
+    let delta = (b - a + 180.0).rem_euclid(360.0) - 180.0;
+    (a + delta * t).rem_euclid(360.0)
+}
+
+// bezier evaluates a one dimensional cubic bezier running from 0 to 1 through control values p1
+// and p2
+fn bezier(p1: FloatType, p2: FloatType, t: FloatType) -> FloatType {
+    let inv = 1.0 - t;
+    3.0 * inv * inv * t * p1 + 3.0 * inv * t * t * p2 + t * t * t
+}
+
+// cubic_bezier evaluates a CSS style timing curve with control points (x1, y1) and (x2, y2) at
+// x = t, solving for the curve parameter by bisection since x is monotonic for x1, x2 in 0..1
+fn cubic_bezier(
+    x1: FloatType,
+    y1: FloatType,
+    x2: FloatType,
+    y2: FloatType,
+    t: FloatType,
+) -> FloatType {
+    let t = t.clamp(0.0, 1.0);
+    let (mut low, mut high) = (0.0, 1.0);
+    let mut s = t;
+    for _ in 0..32 {
+        let x = bezier(x1, x2, s);
+        if (x - t).abs() < 1e-7 {
+            break;
+        }
+        if x < t {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+
+    bezier(y1, y2, s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evalexpr::eval_number_with_context;
+
+    fn close(a: FloatType, b: FloatType) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn lerp_hue_takes_the_shorter_way() {
+        assert!(close(lerp_hue(350.0, 10.0, 0.5), 0.0));
+        assert!(close(lerp_hue(10.0, 350.0, 0.5), 0.0));
+        assert!(close(lerp_hue(350.0, 10.0, 0.25), 355.0));
+        assert!(close(lerp_hue(0.0, 90.0, 0.5), 45.0));
+        assert!(close(lerp_hue(-90.0, 0.0, 1.0), 0.0));
+    }
+
+    #[test]
+    fn cubic_bezier_follows_css_timing_curves() {
+        // linear is the identity and ease matches the value browsers use at its midpoint
+        for t in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            assert!(close(cubic_bezier(0.0, 0.0, 1.0, 1.0, t), t));
+        }
+        assert!(close(cubic_bezier(0.25, 0.1, 0.25, 1.0, 0.5), 0.8024));
+        assert!(close(cubic_bezier(0.25, 0.1, 0.25, 1.0, -1.0), 0.0));
+        assert!(close(cubic_bezier(0.25, 0.1, 0.25, 1.0, 2.0), 1.0));
+    }
+
+    #[test]
+    fn registered_functions_accept_ints() {
+        let mut ctx = HashMapContext::new();
+        register_functions(&mut ctx).unwrap();
+        let eval = |expr: &str| eval_number_with_context(expr, &ctx).unwrap();
+
+        assert!(close(eval("lerp_hue(350, 10, 0.5)"), 0.0));
+        assert!(close(eval("clamp(5, 1, 0)"), 1.0));
+        assert!(close(eval("ease_in_out_quad(0.5)"), 0.5));
+    }
+}
@@ -1 +1,8 @@
+pub mod deps;
+pub mod diagnostic;
+pub mod domain;
+pub mod functions;
+pub mod noise;
+pub mod param;
 pub mod parse;
+pub mod validate;
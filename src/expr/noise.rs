@@ -0,0 +1,141 @@
+use super::functions::register;
+use evalexpr::{EvalexprResult, FloatType, HashMapContext};
+
+// register_noise adds the random and noise functions to ctx, each seeded with the project seed so
+// the same project always renders the same palette. Perlin and simplex noise lie in -1..1, value
+// noise and rand lie in 0..1.
+pub fn register_noise(ctx: &mut HashMapContext, seed: u64) -> EvalexprResult<()> {
+    register(ctx, "rand", move |[row_seed, i]| {
+        unit(hash(seed, &[row_seed.to_bits(), i.to_bits()]))
+    })?;
+    register(ctx, "noise1", move |[x]| perlin1(seed, x))?;
+    register(ctx, "noise2", move |[x, y]| perlin2(seed, x, y))?;
+    register(ctx, "value_noise1", move |[x]| value1(seed, x))?;
+    register(ctx, "value_noise2", move |[x, y]| value2(seed, x, y))?;
+    register(ctx, "simplex2", move |[x, y]| simplex2(seed, x, y))?;
+
+    Ok(())
+}
+
+// hash mixes the seed and each key through splitmix64's finalizer
+fn hash(seed: u64, keys: &[u64]) -> u64 {
+    let mut state = seed;
+    for key in keys {
+        state = (state ^ key).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        state ^= state >> 31;
+    }
+
+    state
+}
+
+// unit maps a hash onto 0..1
+fn unit(hash: u64) -> FloatType {
+    (hash >> 11) as FloatType / (1u64 << 53) as FloatType
+}
+
+// lattice_hash hashes an integer lattice point
+fn lattice_hash(seed: u64, point: &[i64]) -> u64 {
+    let keys: Vec<u64> = point.iter().map(|&coord| coord as u64).collect();
+    hash(seed, &keys)
+}
+
+// gradient2 returns the unit gradient of a 2D lattice point
+fn gradient2(seed: u64, x: i64, y: i64) -> (FloatType, FloatType) {
+    let angle = unit(lattice_hash(seed, &[x, y])) * std::f64::consts::TAU;
+    (angle.cos(), angle.sin())
+}
+
+// fade is Perlin's quintic interpolation curve
+fn fade(t: FloatType) -> FloatType {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: FloatType, b: FloatType, t: FloatType) -> FloatType {
+    a + (b - a) * t
+}
+
+fn perlin1(seed: u64, x: FloatType) -> FloatType {
+    let x0 = x.floor();
+    let fx = x - x0;
+    let gradient = |i: i64| unit(lattice_hash(seed, &[i])) * 2.0 - 1.0;
+
+    // a 1D gradient's contribution peaks at half a cell away, so it's doubled to reach -1..1
+    2.0 * lerp(
+        gradient(x0 as i64) * fx,
+        gradient(x0 as i64 + 1) * (fx - 1.0),
+        fade(fx),
+    )
+}
+
+fn perlin2(seed: u64, x: FloatType, y: FloatType) -> FloatType {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let corner = |cx: i64, cy: i64, dx: FloatType, dy: FloatType| {
+        let (gx, gy) = gradient2(seed, cx, cy);
+        gx * dx + gy * dy
+    };
+
+    let bottom = lerp(
+        corner(ix, iy, fx, fy),
+        corner(ix + 1, iy, fx - 1.0, fy),
+        fade(fx),
+    );
+    let top = lerp(
+        corner(ix, iy + 1, fx, fy - 1.0),
+        corner(ix + 1, iy + 1, fx - 1.0, fy - 1.0),
+        fade(fx),
+    );
+
+    // scaled by sqrt(2) since unit gradients in 2D reach at most sqrt(1/2)
+    (lerp(bottom, top, fade(fy)) * std::f64::consts::SQRT_2).clamp(-1.0, 1.0)
+}
+
+fn value1(seed: u64, x: FloatType) -> FloatType {
+    let x0 = x.floor();
+    let value = |i: i64| unit(lattice_hash(seed, &[i]));
+
+    lerp(value(x0 as i64), value(x0 as i64 + 1), fade(x - x0))
+}
+
+fn value2(seed: u64, x: FloatType, y: FloatType) -> FloatType {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let value = |cx: i64, cy: i64| unit(lattice_hash(seed, &[cx, cy]));
+
+    let bottom = lerp(value(ix, iy), value(ix + 1, iy), fade(x - x0));
+    let top = lerp(value(ix, iy + 1), value(ix + 1, iy + 1), fade(x - x0));
+    lerp(bottom, top, fade(y - y0))
+}
+
+fn simplex2(seed: u64, x: FloatType, y: FloatType) -> FloatType {
+    // skew factors between the square and triangular grids
+    let skew = (3f64.sqrt() - 1.0) / 2.0;
+    let unskew = (3.0 - 3f64.sqrt()) / 6.0;
+
+    let s = (x + y) * skew;
+    let (i, j) = ((x + s).floor(), (y + s).floor());
+    let t = (i + j) * unskew;
+    let (x0, y0) = (x - (i - t), y - (j - t));
+
+    // which of the two triangles of the skewed cell the point is in
+    let (i1, j1) = if x0 > y0 { (1.0, 0.0) } else { (0.0, 1.0) };
+    let corners = [
+        (0.0, 0.0, x0, y0),
+        (i1, j1, x0 - i1 + unskew, y0 - j1 + unskew),
+        (1.0, 1.0, x0 - 1.0 + 2.0 * unskew, y0 - 1.0 + 2.0 * unskew),
+    ];
+
+    let mut total = 0.0;
+    for (ci, cj, dx, dy) in corners {
+        let falloff = 0.5 - dx * dx - dy * dy;
+        if falloff > 0.0 {
+            let (gx, gy) = gradient2(seed, (i + ci) as i64, (j + cj) as i64);
+            total += falloff.powi(4) * (gx * dx + gy * dy);
+        }
+    }
+
+    (70.0 * total).clamp(-1.0, 1.0)
+}
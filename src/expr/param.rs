@@ -0,0 +1,56 @@
+use super::parse::ExprRow;
+use evalexpr::{Node, Operator, Value};
+
+// Param is a tunable parameter declared by a row of the form `param(default, min, max)`, which is
+// rendered as a slider rather than evaluated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Param {
+    pub default: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Param {
+    // clamp restricts value to the range of the parameter
+    pub fn clamp(&self, value: f64) -> f64 {
+        value.max(self.min).min(self.max)
+    }
+}
+
+impl ExprRow {
+    // param returns the parameter the row declares, if its whole expression is a call to `param`
+    // with constant arguments
+    pub fn param(&mut self) -> Option<Param> {
+        let call = outermost(self.compile().ok()?);
+        match call.operator() {
+            Operator::FunctionIdentifier { identifier } if identifier == "param" => (),
+            _ => return None,
+        }
+
+        let arguments = call.children().first()?.eval().ok()?;
+        let numbers: Vec<f64> = arguments
+            .as_fixed_len_tuple(3)
+            .ok()?
+            .iter()
+            .map(Value::as_number)
+            .collect::<Result<_, _>>()
+            .ok()?;
+
+        // a reversed range is collapsed onto its minimum so the slider stays well formed
+        Some(Param {
+            default: numbers[0],
+            min: numbers[1],
+            max: numbers[2].max(numbers[1]),
+        })
+    }
+}
+
+// outermost looks through the root nodes wrapping an expression, such as parentheses
+pub fn outermost(tree: &Node) -> &Node {
+    let mut node = tree;
+    while matches!(node.operator(), Operator::RootNode) && node.children().len() == 1 {
+        node = &node.children()[0];
+    }
+
+    node
+}
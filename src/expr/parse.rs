@@ -1,20 +1,99 @@
-use super::super::colorgen::model::{ColorSpace, GenericPaletteSpace};
-use super::super::colorgen::palette_models::{hsv, HsvColorSpace};
+use super::super::colorgen::color::RgbaColor;
+use super::super::colorgen::model::{
+    ColorSpace, ColorSpaceRegistry, GenericPaletteSpace, ModelInput,
+};
+use super::deps::eval_order;
+use super::diagnostic::Diagnostic;
+use super::domain::Domain;
+use super::functions::register_functions;
+use super::noise::register_noise;
+use anyhow::{anyhow, bail, Context as _};
 use bevy::prelude::*;
 use evalexpr::*;
 use ropey::Rope;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, PoisonError, RwLock};
+
+// TIME_VAR is the variable holding the elapsed time in seconds, palettes reading it are animated
+pub const TIME_VAR: &str = "time";
+
+// PREV_PREFIX prefixes a model input to name its value at the previous color, as in `prev_h`
+pub const PREV_PREFIX: &str = "prev_";
 
 // wrapper for HashMapContext that includes a list of "model vars" that
 // shouldn't be user-assigned in the context
 pub struct ExprCtx {
     pub model_vars: Vec<String>,
+
+    // required_model_vars is the number of leading model vars without a default, which the
+    // vector row has to supply at the least
+    pub required_model_vars: usize,
 }
 
 // represents an expression for a row field in the UI
+#[derive(Serialize, Deserialize)]
 pub struct ExprRow {
     pub var: String,
     pub expr: String,
+
+    // diagnostic explains the first failure of expr during the last render, if it failed
+    #[serde(skip)]
+    pub diagnostic: Option<Diagnostic>,
+
+    // compiled caches the operator tree of expr along with the text it was built from, so that it
+    // is only rebuilt once expr is edited
+    #[serde(skip)]
+    compiled: Option<(String, evalexpr::Node)>,
+}
+
+impl ExprRow {
+    pub fn new(var: &str, expr: &str) -> ExprRow {
+        ExprRow {
+            var: var.to_string(),
+            expr: expr.to_string(),
+            diagnostic: None,
+            compiled: None,
+        }
+    }
+
+    // compile returns the operator tree for expr, reusing the cached tree if expr hasn't changed
+    pub fn compile(&mut self) -> EvalexprResult<&evalexpr::Node> {
+        let compiled = match self.compiled.take() {
+            Some((source, tree)) if source == self.expr => (source, tree),
+            _ => (self.expr.clone(), build_operator_tree(&self.expr)?),
+        };
+
+        Ok(&self.compiled.insert(compiled).1)
+    }
+
+    // reads returns whether expr reads var, rows that fail to parse read nothing
+    pub fn reads(&mut self, var: &str) -> bool {
+        self.compile()
+            .map(|tree| tree.iter_read_variable_identifiers().any(|identifier| identifier == var))
+            .unwrap_or(false)
+    }
+
+    // eval evaluates the row against ctx for color index, recording a diagnostic on the row if
+    // it fails and one hasn't already been recorded during this render
+    pub fn eval(&mut self, ctx: &HashMapContext, index: u32) -> anyhow::Result<Value> {
+        let result = match self.compile() {
+            Err(err) => Err(Diagnostic::parse(&self.expr, &err)),
+            Ok(tree) => match tree.eval_with_context(ctx) {
+                Err(err) => Err(Diagnostic::eval(&self.expr, &err, index)),
+                Ok(Value::Float(value)) if !value.is_finite() => {
+                    Err(Diagnostic::non_finite(&self.expr, value, index))
+                }
+                Ok(value) => Ok(value),
+            },
+        };
+
+        result.map_err(|diagnostic| {
+            let err = anyhow!("{}", diagnostic.message);
+            self.diagnostic.get_or_insert(diagnostic);
+            err
+        })
+    }
 }
 
 // represents a list of models, context expressions, and a context for them.
@@ -23,11 +102,40 @@ pub struct ExprList {
     // ctx holds mappings of variables to expressions for evalexpr
     pub ctx: HashMapContext,
 
+    // expr_ctx holds the variables of ctx that user-defined rows may not assign
+    pub expr_ctx: ExprCtx,
+
     // expr_rows contains each user-defined expression row in the expressions list
     pub expr_rows: Vec<ExprRow>,
 
     // model_expr_rows contains each expression row that must be filled for the color model
     pub model_expr_rows: Vec<ExprRow>,
+
+    // initial_expr_rows holds the value of each model input before the first color of each row,
+    // which the first color reads through `prev_<input>` and `prev`. They're evaluated with the
+    // domain variables but before any user-defined row.
+    pub initial_expr_rows: Vec<ExprRow>,
+
+    // vector_expr_row produces every model input at once as a tuple, taking the place of the
+    // model rows while its expression isn't empty
+    pub vector_expr_row: ExprRow,
+
+    // domain holds the number of colors rendered into the palette and where each is sampled
+    pub domain: Domain,
+
+    // seed seeds the random and noise functions available to expressions
+    pub seed: u64,
+
+    // params holds the value each parameter row has been set to by its variable, parameters that
+    // haven't been set take their default
+    pub params: BTreeMap<String, f64>,
+
+    // time is the elapsed time in seconds supplied to expressions as `time`
+    pub time: f64,
+
+    // animated is set by each render to whether any row reads `time`, in which case the palette
+    // is re-rendered every frame
+    pub animated: bool,
 }
 
 // impl ExprList {
@@ -36,48 +144,537 @@ pub struct ExprList {
 
 // expr_list_from_model returns an ExprList for anything that implements ColorSpace
 pub fn expr_list_from_model(model: &dyn ColorSpace) -> ExprList {
-    let model_expr_rows = model
+    let model_expr_rows: Vec<ExprRow> = model
         .inputs()
         .into_iter()
-        .map(|input| ExprRow {
-            var: input.to_string(),
-            expr: String::new(),
-        })
+        .map(|input| ExprRow::new(&input.name, ""))
         .collect();
+    let initial_expr_rows = model_expr_rows
+        .iter()
+        .map(|row| ExprRow::new(&row.var, ""))
+        .collect();
+
+    let model_vars: Vec<String> = model.inputs().iter().map(|input| input.name.clone()).collect();
+    let required_model_vars = model
+        .inputs()
+        .iter()
+        .rposition(|input| input.default.is_none())
+        .map_or(0, |i| i + 1);
+    let vector_expr_row = ExprRow::new(&format!("({})", model_vars.join(", ")), "");
+
+    let mut ctx = HashMapContext::new();
+    // the library is only made of fixed names, so registering it can't fail
+    let _ = register_functions(&mut ctx);
 
     ExprList {
-        ctx: context_map! { "dummy" => 0 }.unwrap(),
-        expr_rows: vec![ExprRow {
-            var: String::new(),
-            expr: String::new(),
-        }],
+        ctx,
+        expr_ctx: ExprCtx {
+            model_vars,
+            required_model_vars,
+        },
+        expr_rows: vec![ExprRow::new("", "")],
         model_expr_rows,
+        initial_expr_rows,
+        vector_expr_row,
+        domain: Domain::default(),
+        seed: 0,
+        params: BTreeMap::new(),
+        time: 0.0,
+        animated: false,
     }
 }
 
-pub fn setup_expr_list(mut commands: Commands) {
-    // later on once I have dynamic default color models I need to replace this:
-    let model = hsv();
-    commands.insert_resource(expr_list_from_model(&model.0));
+pub fn setup_expr_list(mut commands: Commands, registry: Res<ColorSpaceRegistry>) {
+    commands.insert_resource(expr_list_from_model(registry.active()));
 }
 
 impl ExprList {
-    // render_rgb_hexes_simple_domain renders expressions into a simple list of RGB hex colors,
-    // generating a color for each number from 0 to color_count and supplying it as ctx var `x`
+    // switch_model rebuilds the list for a new color model, keeping the user-defined rows, the
+    // domain, the seed, and the expressions of any model row whose input the new model shares
+    pub fn switch_model(&mut self, model: &dyn ColorSpace) {
+        let mut expr_list = expr_list_from_model(model);
+        carry_exprs(&mut expr_list.model_expr_rows, &mut self.model_expr_rows);
+        carry_exprs(&mut expr_list.initial_expr_rows, &mut self.initial_expr_rows);
+        carry_exprs(
+            std::slice::from_mut(&mut expr_list.vector_expr_row),
+            std::slice::from_mut(&mut self.vector_expr_row),
+        );
+        expr_list.expr_rows = std::mem::take(&mut self.expr_rows);
+        expr_list.domain = self.domain.clone();
+        expr_list.seed = self.seed;
+        expr_list.params = std::mem::take(&mut self.params);
+
+        *self = expr_list;
+    }
+
+    // render_rgb_hexes_simple_domain renders expressions into a simple list of RGBA colors,
+    // generating a color for each index of the domain and supplying the domain variables to ctx
     pub fn render_rgb_hexes_simple_domain(
         &mut self,
         color_model: &dyn ColorSpace,
-        color_count: u32,
-    ) {
+    ) -> anyhow::Result<Palette> {
+        // a list that fails before rendering isn't re-rendered every frame, it waits for an edit
+        self.animated = false;
+        // diagnostics are cleared first so that a list failing as a whole doesn't keep those of
+        // the last render
+        for row in self.rows_mut() {
+            row.diagnostic = None;
+        }
+        self.ensure_valid()?;
+        let order = eval_order(&mut self.expr_rows)?;
+        register_noise(&mut self.ctx, self.seed).context("failed to seed noise functions")?;
+        let animated = self.rows_mut().any(|row| row.reads(TIME_VAR));
+        self.animated = animated;
+
+        let mut palette = Palette {
+            columns: self.domain.columns(),
+            rows: self.domain.row_count,
+            model: color_model.name().to_string(),
+            ..default()
+        };
         let mut evaluated_inputs_map: HashMap<String, f32> = Default::default();
-        for n in 0..color_count {
-            let _ = self.ctx.set_value("x".to_string(), (n as f64).into());
-            for input in color_model.inputs() {
-                if let Ok(Value::Float(evaluated_input)) = eval_with_context(input, &self.ctx) {
-                    evaluated_inputs_map.insert(input.to_string(), evaluated_input as f32);
+        // previous_inputs holds the inputs of the last color, which is only read while that color
+        // rendered successfully. It's shared with `prev` so the function is registered once per
+        // render rather than rebuilt for every color.
+        let previous_inputs: Arc<RwLock<HashMap<String, f32>>> = Default::default();
+        register_prev(&mut self.ctx, previous_inputs.clone())
+            .context("failed to register `prev`")?;
+        for n in 0..self.domain.len()? {
+            match self.render_color(
+                color_model,
+                &order,
+                n,
+                &previous_inputs,
+                &mut evaluated_inputs_map,
+            ) {
+                Ok(color) => {
+                    palette.colors.push(color);
+                    palette.inputs.push(evaluated_inputs_map.clone());
+                    let mut previous =
+                        previous_inputs.write().unwrap_or_else(PoisonError::into_inner);
+                    std::mem::swap(&mut *previous, &mut evaluated_inputs_map);
+                }
+                Err(err) => {
+                    palette.colors.push(RgbaColor::TRANSPARENT);
+                    palette.inputs.push(HashMap::new());
+                    palette.errors.push((n, err));
+                    previous_inputs.write().unwrap_or_else(PoisonError::into_inner).clear();
+                }
+            }
+        }
+
+        Ok(palette)
+    }
+
+    // render_color renders color index n, leaving the inputs it evaluated in evaluated_inputs_map
+    fn render_color(
+        &mut self,
+        color_model: &dyn ColorSpace,
+        order: &[usize],
+        n: u32,
+        previous_inputs: &RwLock<HashMap<String, f32>>,
+        evaluated_inputs_map: &mut HashMap<String, f32>,
+    ) -> anyhow::Result<RgbaColor> {
+        // variables are cleared for each index so no row sees a value left over from the last
+        self.ctx.clear_variables();
+        evaluated_inputs_map.clear();
+        self.domain
+            .set_vars(&mut self.ctx, n)
+            .and_then(|()| self.ctx.set_value(TIME_VAR.into(), self.time.into()))
+            .context("failed to assign domain variables")
+            .and_then(|()| self.set_prev_vars(color_model, n, previous_inputs))
+            .and_then(|()| self.eval_user_rows(order, n))
+            .and_then(|()| self.eval_model_rows(color_model, n, evaluated_inputs_map))
+            .and_then(|()| color_model.as_rgba(evaluated_inputs_map))
+            .with_context(|| format!("failed to render color {}", n))
+    }
+
+    // rows_mut iterates over every row of the list
+    fn rows_mut(&mut self) -> impl Iterator<Item = &mut ExprRow> {
+        self.expr_rows
+            .iter_mut()
+            .chain(self.model_expr_rows.iter_mut())
+            .chain(self.initial_expr_rows.iter_mut())
+            .chain(std::iter::once(&mut self.vector_expr_row))
+    }
+
+    // set_prev_vars supplies the inputs of the previous color to ctx as `prev_<input>`, and to
+    // `prev("<input>")` through previous_inputs. The first color of each row sees the initial
+    // values instead, where an empty initial expression falls back to the input's default or
+    // leaves the input unset.
+    fn set_prev_vars(
+        &mut self,
+        color_model: &dyn ColorSpace,
+        index: u32,
+        previous_inputs: &RwLock<HashMap<String, f32>>,
+    ) -> anyhow::Result<()> {
+        if index % self.domain.columns() == 0 {
+            // the initial values are collected before they're stored, since an initial expression
+            // calling `prev` reads previous_inputs
+            previous_inputs.write().unwrap_or_else(PoisonError::into_inner).clear();
+            let mut initial_inputs: HashMap<String, f32> = HashMap::new();
+            for (row, input) in self.initial_expr_rows.iter_mut().zip(color_model.inputs()) {
+                if row.expr.trim().is_empty() {
+                    if let Some(default) = input.default {
+                        initial_inputs.insert(row.var.clone(), default);
+                    }
+                    continue;
+                }
+
+                let initial = eval_number(row, &self.ctx, index)
+                    .with_context(|| format!("failed to evaluate initial `{}`", row.var))?;
+                let constrained = constrain_row(row, input, initial, index)?;
+                initial_inputs.insert(row.var.clone(), constrained);
+            }
+            *previous_inputs.write().unwrap_or_else(PoisonError::into_inner) = initial_inputs;
+        }
+
+        let previous_inputs = previous_inputs.read().unwrap_or_else(PoisonError::into_inner);
+        for (var, value) in previous_inputs.iter() {
+            self.ctx
+                .set_value(format!("{}{}", PREV_PREFIX, var), (*value as f64).into())
+                .with_context(|| format!("failed to assign `{}{}`", PREV_PREFIX, var))?;
+        }
+
+        Ok(())
+    }
+
+    // eval_user_rows evaluates each user-defined row in the given order, assigning each result to
+    // the row's variable in ctx so that later rows and the model rows can reference it
+    fn eval_user_rows(&mut self, order: &[usize], index: u32) -> anyhow::Result<()> {
+        for &i in order {
+            let row = &mut self.expr_rows[i];
+            if row.var.is_empty() {
+                continue;
+            }
+
+            let value = match row.param() {
+                Some(param) => {
+                    let value = self.params.get(&row.var).copied().unwrap_or(param.default);
+                    Value::Float(param.clamp(value))
+                }
+                None => row
+                    .eval(&self.ctx, index)
+                    .with_context(|| format!("failed to evaluate row `{}`", row.var))?,
+            };
+            self.ctx
+                .set_value(row.var.clone(), value)
+                .with_context(|| format!("failed to assign row `{}`", row.var))?;
+        }
+
+        Ok(())
+    }
+
+    // eval_model_rows evaluates the expression of each model row against the current ctx,
+    // storing the constrained result for each model input in evaluated_inputs_map
+    fn eval_model_rows(
+        &mut self,
+        color_model: &dyn ColorSpace,
+        index: u32,
+        evaluated_inputs_map: &mut HashMap<String, f32>,
+    ) -> anyhow::Result<()> {
+        if !self.vector_expr_row.expr.trim().is_empty() {
+            return self.eval_vector_row(color_model, index, evaluated_inputs_map);
+        }
+
+        for (row, input) in self.model_expr_rows.iter_mut().zip(color_model.inputs()) {
+            // optional inputs fall back to their default when left empty
+            if let Some(default) = input.default.filter(|_| row.expr.trim().is_empty()) {
+                evaluated_inputs_map.insert(row.var.clone(), default);
+                continue;
+            }
+
+            let evaluated_input = eval_number(row, &self.ctx, index)
+                .with_context(|| format!("failed to evaluate input `{}`", row.var))?;
+            let constrained = constrain_row(row, input, evaluated_input, index)?;
+            evaluated_inputs_map.insert(row.var.clone(), constrained);
+        }
+
+        Ok(())
+    }
+}
+
+// carry_exprs moves the expression of each old row into the new row with the same variable
+pub fn carry_exprs(rows: &mut [ExprRow], old_rows: &mut [ExprRow]) {
+    for row in rows.iter_mut() {
+        if let Some(old_row) = old_rows.iter_mut().find(|old_row| old_row.var == row.var) {
+            row.expr = std::mem::take(&mut old_row.expr);
+        }
+    }
+}
+
+impl ExprList {
+    // eval_vector_row evaluates the vector row into a value for each model input, inputs left off
+    // the end of the tuple fall back to their defaults
+    fn eval_vector_row(
+        &mut self,
+        color_model: &dyn ColorSpace,
+        index: u32,
+        evaluated_inputs_map: &mut HashMap<String, f32>,
+    ) -> anyhow::Result<()> {
+        let row = &mut self.vector_expr_row;
+        let values = match row
+            .eval(&self.ctx, index)
+            .with_context(|| format!("failed to evaluate `{}`", row.var))?
+        {
+            Value::Tuple(values) => values,
+            value => {
+                let message = format!(
+                    "expected a tuple but the expression evaluated to {}",
+                    value_type_name(&value)
+                );
+                row.diagnostic
+                    .get_or_insert_with(|| Diagnostic::wrong_shape(&row.expr, &message, index));
+                bail!(
+                    "`{}` evaluated to {} `{}` instead of a tuple",
+                    row.var,
+                    value_type_name(&value),
+                    value
+                )
+            }
+        };
+        if let Err(message) = self.expr_ctx.check_arity(values.len()) {
+            row.diagnostic
+                .get_or_insert_with(|| Diagnostic::wrong_shape(&row.expr, &message, index));
+            bail!("`{}` is invalid: {}", row.var, message);
+        }
+
+        for (i, input) in color_model.inputs().iter().enumerate() {
+            let evaluated_input = match values.get(i) {
+                Some(Value::Float(value)) if !value.is_finite() => {
+                    row.diagnostic
+                        .get_or_insert_with(|| Diagnostic::non_finite(&row.expr, *value, index));
+                    bail!("input `{}` evaluated to {}", input.name, value)
                 }
+                Some(Value::Float(value)) => *value,
+                Some(Value::Int(value)) => *value as f64,
+                Some(value) => {
+                    row.diagnostic.get_or_insert_with(|| {
+                        Diagnostic::not_a_number(&row.expr, value_type_name(value), index)
+                    });
+                    bail!(
+                        "input `{}` evaluated to {} `{}` instead of a number",
+                        input.name,
+                        value_type_name(value),
+                        value
+                    )
+                }
+                // check_arity guarantees each input left off has a default
+                None => input.default.unwrap_or_default() as f64,
+            };
+            let constrained = constrain_row(row, input, evaluated_input, index)?;
+            evaluated_inputs_map.insert(input.name.clone(), constrained);
+        }
+
+        Ok(())
+    }
+}
+
+impl Palette {
+    // rows_iter iterates over each row of colors in order
+    pub fn rows_iter(&self) -> impl Iterator<Item = &[RgbaColor]> {
+        self.colors.chunks(self.columns.max(1) as usize)
+    }
+}
+
+// register_prev adds `prev("<input>")` to ctx, reading the inputs of the previous color from
+// previous_inputs
+fn register_prev(
+    ctx: &mut HashMapContext,
+    previous_inputs: Arc<RwLock<HashMap<String, f32>>>,
+) -> EvalexprResult<()> {
+    ctx.set_function(
+        "prev".into(),
+        Function::new(move |argument| {
+            let var = argument.as_string()?;
+            let previous_inputs = previous_inputs.read().unwrap_or_else(PoisonError::into_inner);
+            match previous_inputs.get(&var) {
+                Some(value) => Ok(Value::Float(*value as f64)),
+                None => Err(EvalexprError::CustomMessage(format!(
+                    "input `{}` has no previous value",
+                    var
+                ))),
             }
-            color_model.as_rgba_hex(&evaluated_inputs_map);
+        }),
+    )
+}
+
+// constrain_row constrains the value row evaluated to for input, recording a diagnostic on the
+// row if the value is out of range
+fn constrain_row(
+    row: &mut ExprRow,
+    input: &ModelInput,
+    value: f64,
+    index: u32,
+) -> anyhow::Result<f32> {
+    input.constrain(value as f32).map_err(|err| {
+        row.diagnostic
+            .get_or_insert_with(|| Diagnostic::out_of_range(&row.expr, &err.to_string(), index));
+        err
+    })
+}
+
+// eval_number evaluates row as a number, ints are coerced to floats so that rows like `v = 1`
+// behave as expected
+fn eval_number(row: &mut ExprRow, ctx: &HashMapContext, index: u32) -> anyhow::Result<f64> {
+    match row.eval(ctx, index)? {
+        Value::Float(value) => Ok(value),
+        Value::Int(value) => Ok(value as f64),
+        value => {
+            row.diagnostic.get_or_insert_with(|| {
+                Diagnostic::not_a_number(&row.expr, value_type_name(&value), index)
+            });
+            bail!(
+                "expression evaluated to {} `{}` instead of a number",
+                value_type_name(&value),
+                value
+            )
+        }
+    }
+}
+
+// value_type_name returns a user-facing name for the type of an evaluated value
+fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "string",
+        Value::Float(_) => "float",
+        Value::Int(_) => "int",
+        Value::Boolean(_) => "boolean",
+        Value::Tuple(_) => "tuple",
+        Value::Empty => "nothing",
+    }
+}
+
+// Palette holds the most recently rendered colors along with the error for each color index that
+// failed to render
+#[derive(Resource, Default)]
+pub struct Palette {
+    // colors holds the color for each color index row by row, indices that failed are left
+    // transparent
+    pub colors: Vec<RgbaColor>,
+
+    // columns and rows are the dimensions of the grid the colors are laid out in
+    pub columns: u32,
+    pub rows: u32,
+
+    // model is the name of the color model the palette was rendered with, and inputs holds the
+    // value of each of its inputs for each color index, so that exports can write colors in the
+    // model they were authored in. Indices that failed have no inputs.
+    pub model: String,
+    pub inputs: Vec<HashMap<String, f32>>,
+
+    // errors holds the color index and cause of each failed color
+    pub errors: Vec<(u32, anyhow::Error)>,
+
+    // failure is why the list as a whole couldn't be rendered, such as rows that form a cycle, in
+    // which case the palette has no colors
+    pub failure: Option<String>,
+}
+
+// render_palette re-renders the Palette resource whenever the expressions or color model change,
+// and every frame while the expressions read `time`. A list that fails to render replaces the
+// palette with one holding only the failure, so nothing keeps using colors from before the edit.
+pub fn render_palette(
+    mut commands: Commands,
+    mut expr_list: ResMut<ExprList>,
+    registry: Res<ColorSpaceRegistry>,
+    time: Res<Time>,
+) -> anyhow::Result<()> {
+    if !expr_list.is_changed() && !registry.is_changed() && !expr_list.animated {
+        return Ok(());
+    }
+
+    let expr_list = expr_list.bypass_change_detection();
+    expr_list.time = time.elapsed_seconds_f64();
+    match expr_list.render_rgb_hexes_simple_domain(registry.active()) {
+        Ok(palette) => {
+            commands.insert_resource(palette);
+            Ok(())
+        }
+        Err(err) => {
+            commands.insert_resource(Palette {
+                failure: Some(format!("{:#}", err)),
+                ..default()
+            });
+            Err(err)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::colorgen::palette_models::oklch;
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    // benchmark_list returns a 1024 color OKLCh list with helper, param and prev rows
+    fn benchmark_list(model: &dyn ColorSpace) -> ExprList {
+        let mut expr_list = expr_list_from_model(model);
+        expr_list.domain.color_count = 64;
+        expr_list.domain.row_count = 16;
+        expr_list.expr_rows = vec![
+            ExprRow::new("wave", "math::sin(t * 12.566) * 0.5 + 0.5"),
+            ExprRow::new("lift", "param(0.25, 0, 0.5)"),
+            ExprRow::new("base_h", "ty * 360 + wave * 30"),
+        ];
+        expr_list.initial_expr_rows[0].expr = "0.2".to_string();
+        for (row, expr) in expr_list.model_expr_rows.iter_mut().zip([
+            "clamp(prev_l + lift / count, 0, 1)",
+            "0.05 + wave * 0.1",
+            "base_h",
+            "1",
+        ]) {
+            row.expr = expr.to_string();
+        }
+
+        expr_list
+    }
+
+    // time_render times rendering every color of expr_list, dropping the compiled trees of every
+    // row before each color unless cached
+    fn time_render(expr_list: &mut ExprList, model: &dyn ColorSpace, cached: bool) -> Duration {
+        expr_list.ensure_valid().unwrap();
+        let order = eval_order(&mut expr_list.expr_rows).unwrap();
+        let previous_inputs: Arc<RwLock<HashMap<String, f32>>> = Default::default();
+        register_prev(&mut expr_list.ctx, previous_inputs.clone()).unwrap();
+        let mut evaluated_inputs_map = HashMap::new();
+
+        let start = Instant::now();
+        for n in 0..expr_list.domain.len().unwrap() {
+            if !cached {
+                for row in expr_list.rows_mut() {
+                    row.compiled = None;
+                }
+            }
+            expr_list
+                .render_color(model, &order, n, &previous_inputs, &mut evaluated_inputs_map)
+                .unwrap();
+            std::mem::swap(&mut *previous_inputs.write().unwrap(), &mut evaluated_inputs_map);
+        }
+
+        start.elapsed()
+    }
+
+    #[test]
+    fn cached_trees_render_faster() {
+        let model = oklch().with_alpha();
+        let mut expr_list = benchmark_list(&model);
+        let palette = expr_list.render_rgb_hexes_simple_domain(&model).unwrap();
+        assert_eq!(palette.colors.len(), 1024);
+        assert!(palette.errors.is_empty(), "{:?}", palette.errors.first());
+
+        // the fastest of a few runs of each is compared, to keep the test from being flaky
+        let fastest = |expr_list: &mut ExprList, cached: bool| {
+            (0..3).map(|_| time_render(expr_list, &model, cached)).min().unwrap()
+        };
+        let cached = fastest(&mut expr_list, true);
+        let uncached = fastest(&mut expr_list, false);
+        println!("cached trees: {:?}, parsed per color: {:?}", cached, uncached);
+        assert!(
+            uncached > cached * 3 / 2,
+            "cached trees took {:?}, parsing per color took {:?}",
+            cached,
+            uncached
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use super::domain::DOMAIN_VARS;
+use super::param::outermost;
+use super::parse::{ExprCtx, ExprList, ExprRow, PREV_PREFIX, TIME_VAR};
+use anyhow::bail;
+use evalexpr::{Node, Operator};
+
+// Severity is how a RowIssue affects rendering, errors stop the palette from rendering while
+// warnings are only reported
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+// RowIssue is a problem found with a user-defined row's variable or expression
+#[derive(Clone, Debug)]
+pub struct RowIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl ExprCtx {
+    // validate_row checks a user-defined row against the variables reserved by the color model and
+    // the domain
+    pub fn validate_row(&self, row: &mut ExprRow) -> Vec<RowIssue> {
+        let mut issues: Vec<RowIssue> = vec![];
+
+        if self.model_vars.contains(&row.var) {
+            issues.push(RowIssue {
+                severity: Severity::Error,
+                message: format!("`{}` is an input of the color model", row.var),
+            });
+        } else if DOMAIN_VARS.contains(&row.var.as_str()) {
+            issues.push(RowIssue {
+                severity: Severity::Error,
+                message: format!("`{}` is reserved for the domain", row.var),
+            });
+        } else if row.var == TIME_VAR {
+            issues.push(RowIssue {
+                severity: Severity::Error,
+                message: format!("`{}` is reserved for the elapsed time", row.var),
+            });
+        } else if row
+            .var
+            .strip_prefix(PREV_PREFIX)
+            .is_some_and(|var| self.model_vars.iter().any(|model_var| model_var == var))
+        {
+            issues.push(RowIssue {
+                severity: Severity::Error,
+                message: format!("`{}` is reserved for the previous color", row.var),
+            });
+        }
+
+        // user rows are evaluated before the model rows, so a model input read here is never set
+        if let Ok(tree) = row.compile() {
+            let mut identifiers: Vec<&str> = tree.iter_read_variable_identifiers().collect();
+            identifiers.sort_unstable();
+            identifiers.dedup();
+            for identifier in identifiers {
+                if self.model_vars.iter().any(|var| var == identifier) {
+                    issues.push(RowIssue {
+                        severity: Severity::Warning,
+                        message: format!(
+                            "`{}` is a model input and isn't available to other rows",
+                            identifier
+                        ),
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+
+    // validate_model checks that no model input shares its name with a variable supplied by the
+    // domain or the time, which would leave the model row reading the supplied value instead
+    pub fn validate_model(&self) -> Vec<RowIssue> {
+        self.model_vars
+            .iter()
+            .filter(|var| DOMAIN_VARS.contains(&var.as_str()) || *var == TIME_VAR)
+            .map(|var| RowIssue {
+                severity: Severity::Error,
+                message: format!(
+                    "model input `{}` collides with a variable supplied to expressions",
+                    var
+                ),
+            })
+            .collect()
+    }
+
+    // check_arity checks that a tuple of arity values supplies every model input, where trailing
+    // inputs with a default may be left off
+    pub fn check_arity(&self, arity: usize) -> Result<(), String> {
+        if arity >= self.required_model_vars && arity <= self.model_vars.len() {
+            return Ok(());
+        }
+
+        let expected = if self.required_model_vars == self.model_vars.len() {
+            self.model_vars.len().to_string()
+        } else {
+            format!("{} to {}", self.required_model_vars, self.model_vars.len())
+        };
+        Err(format!(
+            "expected a tuple of {} values for ({}) but found {}",
+            expected,
+            self.model_vars.join(", "),
+            arity
+        ))
+    }
+
+    // validate_vector_row checks the arity of the vector row when its expression is a tuple
+    // literal, other expressions are only checked once they're evaluated
+    pub fn validate_vector_row(&self, row: &mut ExprRow) -> Vec<RowIssue> {
+        let arity = row.compile().ok().and_then(tuple_arity);
+
+        match arity.map(|arity| self.check_arity(arity)) {
+            Some(Err(message)) => vec![RowIssue {
+                severity: Severity::Error,
+                message,
+            }],
+            _ => vec![],
+        }
+    }
+}
+
+// tuple_arity returns the number of values of a tuple literal, looking through any parentheses
+fn tuple_arity(tree: &Node) -> Option<usize> {
+    let node = outermost(tree);
+    match node.operator() {
+        Operator::Tuple => Some(node.children().len()),
+        _ => None,
+    }
+}
+
+impl ExprList {
+    // validate returns the issues found for each user-defined row, in the same order as expr_rows
+    pub fn validate(&mut self) -> Vec<Vec<RowIssue>> {
+        let expr_ctx = &self.expr_ctx;
+        self.expr_rows
+            .iter_mut()
+            .map(|row| expr_ctx.validate_row(row))
+            .collect()
+    }
+
+    // ensure_valid fails with the first error-level issue found in the color model, the domain,
+    // the user-defined rows or the vector row
+    pub fn ensure_valid(&mut self) -> anyhow::Result<()> {
+        if let Some(issue) = self.expr_ctx.validate_model().first() {
+            bail!("color model is invalid: {}", issue.message);
+        }
+        self.domain.validate()?;
+        let row_issues = self.validate();
+        for (row, issues) in self.expr_rows.iter().zip(row_issues) {
+            if let Some(issue) = issues.iter().find(|i| i.severity == Severity::Error) {
+                bail!("row `{}` is invalid: {}", row.var, issue.message);
+            }
+        }
+        let vector_row = &mut self.vector_expr_row;
+        if let Some(issue) = self.expr_ctx.validate_vector_row(vector_row).first() {
+            bail!("`{}` is invalid: {}", vector_row.var, issue.message);
+        }
+
+        Ok(())
+    }
+}
@@ -5,12 +5,14 @@ use bevy::{
 };
 use bevy_egui::EguiPlugin;
 use colorgen::palette_models::setup_model_resources;
-use expr::parse::setup_expr_list;
+use expr::parse::{render_palette, setup_expr_list};
 use std::f32::consts::PI;
 use ui::egui::ui_example_system;
 
 mod colorgen;
+mod export;
 mod expr;
+mod project;
 mod ui;
 
 enum CollissionState {
@@ -274,10 +276,13 @@ fn main() {
         .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
         .add_plugins(EguiPlugin)
 
-        .add_systems(Startup, (setup, setup_model_resources))
-        .add_systems(Startup, (setup_expr_list))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Startup,
+            (setup_model_resources, apply_deferred, setup_expr_list).chain(),
+        )
 
-        .add_systems(Update, ui_example_system)
+        .add_systems(Update, (ui_example_system, render_palette.pipe(error_handler)))
         .add_systems(Update, (process_physics, apply_physics))
         .add_systems(
             Update,
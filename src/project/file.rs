@@ -0,0 +1,201 @@
+use super::super::colorgen::model::ColorSpaceRegistry;
+use super::super::expr::domain::Domain;
+use super::super::expr::parse::{carry_exprs, expr_list_from_model, ExprList, ExprRow};
+use anyhow::{bail, Context as _};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// PROJECT_VERSION is the version of the project format written by save_project. Whenever the
+// format changes the version is bumped, and the struct of the previous version is kept so that
+// parse_project can deserialize older files with it and convert them into the current Project.
+pub const PROJECT_VERSION: u32 = 1;
+
+// ProjectHeader is read from a project file before anything else, to pick the version of the
+// format to deserialize the rest with
+#[derive(Deserialize)]
+struct ProjectHeader {
+    version: u32,
+}
+
+// Project is everything needed to restore a ramp, written as RON
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+    pub version: u32,
+
+    // model is the name of the active color model in the ColorSpaceRegistry
+    pub model: String,
+
+    pub expr_rows: Vec<ExprRow>,
+    pub model_expr_rows: Vec<ExprRow>,
+
+    #[serde(default)]
+    pub initial_expr_rows: Vec<ExprRow>,
+    #[serde(default)]
+    pub vector_expr: String,
+    #[serde(default)]
+    pub domain: Domain,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub params: BTreeMap<String, f64>,
+}
+
+impl Project {
+    // from_expr_list captures the project of expr_list rendered with the active model of registry
+    pub fn from_expr_list(expr_list: &ExprList, registry: &ColorSpaceRegistry) -> Project {
+        let copy_rows =
+            |rows: &[ExprRow]| rows.iter().map(|row| ExprRow::new(&row.var, &row.expr)).collect();
+
+        Project {
+            version: PROJECT_VERSION,
+            model: registry.active_name().to_string(),
+            expr_rows: copy_rows(&expr_list.expr_rows),
+            model_expr_rows: copy_rows(&expr_list.model_expr_rows),
+            initial_expr_rows: copy_rows(&expr_list.initial_expr_rows),
+            vector_expr: expr_list.vector_expr_row.expr.clone(),
+            domain: expr_list.domain.clone(),
+            seed: expr_list.seed,
+            params: expr_list.params.clone(),
+        }
+    }
+
+    // apply replaces expr_list with the project and activates its model, model rows are matched
+    // to the model's inputs by name so a file stays loadable if a model's inputs change
+    pub fn apply(
+        mut self,
+        expr_list: &mut ExprList,
+        registry: &mut ColorSpaceRegistry,
+    ) -> anyhow::Result<()> {
+        // the domain is checked first so that a file that fails to load leaves the model alone
+        self.domain.validate()?;
+        registry.set_active(&self.model)?;
+
+        let mut loaded = expr_list_from_model(registry.active());
+        if !self.expr_rows.is_empty() {
+            loaded.expr_rows = self.expr_rows;
+        }
+        carry_exprs(&mut loaded.model_expr_rows, &mut self.model_expr_rows);
+        carry_exprs(&mut loaded.initial_expr_rows, &mut self.initial_expr_rows);
+        loaded.vector_expr_row.expr = self.vector_expr;
+        loaded.domain = self.domain;
+        loaded.seed = self.seed;
+        loaded.params = self.params;
+        *expr_list = loaded;
+
+        Ok(())
+    }
+}
+
+// parse_project reads a project from RON, migrating it from the version it was saved with. Files
+// from a newer version are read as the current version on a best-effort basis, where any fields
+// this version doesn't know are ignored.
+pub fn parse_project(text: &str) -> anyhow::Result<Project> {
+    let header: ProjectHeader =
+        ron::from_str(text).context("failed to read the version of the project")?;
+
+    let mut project: Project = match header.version {
+        PROJECT_VERSION => ron::from_str(text).context("failed to read the project")?,
+        version if version > PROJECT_VERSION => ron::from_str(text).with_context(|| {
+            format!(
+                "failed to read a project saved as version {}, newer than version {}",
+                version, PROJECT_VERSION
+            )
+        })?,
+        version => bail!("project version {} isn't supported", version),
+    };
+    project.version = PROJECT_VERSION;
+
+    Ok(project)
+}
+
+// save_project writes expr_list and the active model to path
+pub fn save_project(
+    path: &Path,
+    expr_list: &ExprList,
+    registry: &ColorSpaceRegistry,
+) -> anyhow::Result<()> {
+    let project = Project::from_expr_list(expr_list, registry);
+    let text = ron::ser::to_string_pretty(&project, ron::ser::PrettyConfig::new())
+        .context("failed to serialize the project")?;
+    std::fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+// load_project reads the project at path into expr_list and registry
+pub fn load_project(
+    path: &Path,
+    expr_list: &mut ExprList,
+    registry: &mut ColorSpaceRegistry,
+) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse_project(&text)
+        .and_then(|project| project.apply(expr_list, registry))
+        .with_context(|| format!("failed to load {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::colorgen::palette_models::{hsv, oklch};
+    use super::super::super::expr::domain::Spacing;
+    use super::*;
+
+    #[test]
+    fn round_trips_a_project() {
+        let mut registry = ColorSpaceRegistry::new(hsv().with_alpha());
+        registry.register(oklch().with_alpha());
+        registry.set_active("oklch").unwrap();
+
+        let mut expr_list = expr_list_from_model(registry.active());
+        expr_list.expr_rows = vec![ExprRow::new("lift", "param(0.2, 0, 1)")];
+        expr_list.model_expr_rows[0].expr = "0.3 + t * lift".to_string();
+        expr_list.initial_expr_rows[2].expr = "120".to_string();
+        expr_list.vector_expr_row.expr = "(0.5, 0.1, 200)".to_string();
+        expr_list.domain.color_count = 11;
+        expr_list.domain.spacing = Spacing::Power(1.5);
+        expr_list.domain.row_count = 3;
+        expr_list.seed = 42;
+        expr_list.params.insert("lift".to_string(), 0.6);
+
+        let path = std::env::temp_dir().join(format!("ramp-project-{}.ron", std::process::id()));
+        save_project(&path, &expr_list, &registry).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        registry.set_active("hsv").unwrap();
+        let mut loaded = expr_list_from_model(registry.active());
+        parse_project(&text)
+            .unwrap()
+            .apply(&mut loaded, &mut registry)
+            .unwrap();
+
+        let exprs = |rows: &[ExprRow]| -> Vec<(String, String)> {
+            rows.iter().map(|row| (row.var.clone(), row.expr.clone())).collect()
+        };
+        assert_eq!(registry.active_name(), "oklch");
+        assert_eq!(exprs(&loaded.expr_rows), exprs(&expr_list.expr_rows));
+        assert_eq!(exprs(&loaded.model_expr_rows), exprs(&expr_list.model_expr_rows));
+        assert_eq!(exprs(&loaded.initial_expr_rows), exprs(&expr_list.initial_expr_rows));
+        assert_eq!(loaded.vector_expr_row.expr, expr_list.vector_expr_row.expr);
+        assert_eq!(loaded.domain.color_count, 11);
+        assert_eq!(loaded.domain.spacing, Spacing::Power(1.5));
+        assert_eq!(loaded.domain.row_count, 3);
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.params, expr_list.params);
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_oversized_domains() {
+        assert!(parse_project("(version: 0, model: \"hsv\", expr_rows: [], model_expr_rows: [])")
+            .is_err());
+
+        let mut registry = ColorSpaceRegistry::new(hsv().with_alpha());
+        let mut expr_list = expr_list_from_model(registry.active());
+        let project = parse_project(
+            "(version: 1, model: \"hsv\", expr_rows: [], model_expr_rows: [], \
+             domain: (color_count: 100000, row_count: 100000))",
+        )
+        .unwrap();
+        assert!(project.apply(&mut expr_list, &mut registry).is_err());
+    }
+}
@@ -1,29 +1,519 @@
-use super::super::colorgen::model::{GenericPaletteSpace, ColorSpace};
-use super::super::colorgen::palette_models::HsvColorSpace;
-use super::super::expr::parse::{ExprList, ExprRow};
+use super::super::colorgen::color::RgbaColor;
+use super::super::colorgen::model::ColorSpaceRegistry;
+use super::super::export::ase::{import_ase, ImportedGroup};
+use super::super::export::texture::ImageLayout;
+use super::super::export::tokens::RampNaming;
+use super::super::export::{export_palette, ExportOptions};
+use super::super::expr::parse::{ExprList, ExprRow, Palette};
+use super::super::expr::domain::{Domain, Spacing, COLUMN_RANGE, EXPONENT_RANGE, ROW_RANGE};
+use super::super::expr::param::Param;
+use super::super::expr::validate::{RowIssue, Severity};
+use super::super::project::file::{load_project, save_project};
 use bevy_egui::{egui, EguiContexts};
 use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// ProjectUi holds the path typed into the project controls and the outcome of the last save or
+// load
+pub struct ProjectUi {
+    pub path: String,
+    pub status: Option<Result<String, String>>,
+}
+
+impl Default for ProjectUi {
+    fn default() -> ProjectUi {
+        ProjectUi {
+            path: "ramp.ron".to_string(),
+            status: None,
+        }
+    }
+}
+
+// ExportUi holds the path typed into the export controls, whose extension picks the format, the
+// options of the formats, and the outcome of the last export
+pub struct ExportUi {
+    pub path: String,
+    pub options: ExportOptions,
+    pub status: Option<Result<String, String>>,
+}
+
+impl Default for ExportUi {
+    fn default() -> ExportUi {
+        ExportUi {
+            path: "ramp.gpl".to_string(),
+            options: ExportOptions::default(),
+            status: None,
+        }
+    }
+}
+
+// ImportUi holds the path typed into the import controls, the outcome of the last import, and
+// the groups of swatches it imported
+pub struct ImportUi {
+    pub path: String,
+    pub status: Option<Result<String, String>>,
+    pub groups: Vec<ImportedGroup>,
+}
+
+impl Default for ImportUi {
+    fn default() -> ImportUi {
+        ImportUi {
+            path: "swatches.ase".to_string(),
+            status: None,
+            groups: vec![],
+        }
+    }
+}
 
 pub fn ui_example_system(
     mut contexts: EguiContexts,
-    expr_list_res: ResMut<ExprList>,
-    colorspace_res: ResMut<HsvColorSpace>,
+    mut expr_list_res: ResMut<ExprList>,
+    mut registry: ResMut<ColorSpaceRegistry>,
+    palette_res: Option<Res<Palette>>,
+    mut project_ui: Local<ProjectUi>,
+    mut export_ui: Local<ExportUi>,
+    mut import_ui: Local<ImportUi>,
 ) {
     egui::Window::new("Hello").show(contexts.ctx_mut(), |ui| {
         ui.heading("rampcon");
-        // for 
-        // egui_expr_row(ui, )
-        // ui.add(egui::Slider::new(&mut age, 0..=120).text("age"));
-        // if ui.button("Click each year").clicked() {
-        //     age += 1;
-        // }
-        // ui.label(format!("Hello '{name}', age {age}"));
+        egui_project(ui, &mut project_ui, &mut expr_list_res, &mut registry);
+
+        let mut selected_model = registry.active_name().to_string();
+        egui::ComboBox::from_label("color model")
+            .selected_text(&selected_model)
+            .show_ui(ui, |ui| {
+                for name in registry.color_spaces.keys() {
+                    ui.selectable_value(&mut selected_model, name.clone(), name);
+                }
+            });
+        if selected_model != registry.active_name() && registry.set_active(&selected_model).is_ok()
+        {
+            expr_list_res.switch_model(registry.active());
+        }
+        egui_row_issues(ui, &expr_list_res.expr_ctx.validate_model());
+
+        // rows are edited without change detection so that the palette is only re-rendered when
+        // an edit actually happens
+        let expr_list = expr_list_res.bypass_change_detection();
+        let row_issues = expr_list.validate();
+        let mut changed = false;
+        for (expr_row, issues) in expr_list.expr_rows.iter_mut().zip(&row_issues) {
+            changed |= egui_expr_row(ui, expr_row);
+            if let Some(param) = expr_row.param() {
+                changed |= egui_param(ui, &mut expr_list.params, &expr_row.var, param);
+            }
+            egui_row_issues(ui, issues);
+            egui_row_diagnostic(ui, expr_row);
+        }
+        if ui.button("+").clicked() {
+            expr_list.expr_rows.push(ExprRow::new("", ""));
+            changed = true;
+        }
+
+        ui.separator();
+        changed |= egui_domain(ui, &mut expr_list.domain);
+        ui.horizontal(|ui| {
+            ui.label("seed");
+            changed |= ui.add(egui::DragValue::new(&mut expr_list.seed)).changed();
+        });
+
+        // the vector row replaces the model rows while it's filled in
+        ui.separator();
+        let vector_issues = expr_list
+            .expr_ctx
+            .validate_vector_row(&mut expr_list.vector_expr_row);
+        ui.horizontal(|ui| {
+            ui.label(&expr_list.vector_expr_row.var);
+            changed |= egui_expr_field(ui, &mut expr_list.vector_expr_row, None).changed();
+        });
+        egui_row_issues(ui, &vector_issues);
+        egui_row_diagnostic(ui, &expr_list.vector_expr_row);
+        let vector_empty = expr_list.vector_expr_row.expr.trim().is_empty();
+
+        // each model row is followed by its initial value, read through `prev_<input>` by the
+        // first color of each row
+        for ((model_expr_row, initial_expr_row), input) in expr_list
+            .model_expr_rows
+            .iter_mut()
+            .zip(expr_list.initial_expr_rows.iter_mut())
+            .zip(registry.active().inputs())
+        {
+            let hint = input.default.map(|default| default.to_string());
+            ui.horizontal(|ui| {
+                ui.label(&model_expr_row.var);
+                ui.add_enabled_ui(vector_empty, |ui| {
+                    changed |= egui_expr_field(ui, model_expr_row, hint.clone()).changed();
+                });
+                ui.label("initial");
+                changed |= egui_expr_field(ui, initial_expr_row, hint).changed();
+            });
+            egui_row_diagnostic(ui, model_expr_row);
+            egui_row_diagnostic(ui, initial_expr_row);
+        }
+
+        if changed {
+            expr_list_res.set_changed();
+        }
+
+        if let Some(palette) = &palette_res {
+            ui.separator();
+            if let Some(failure) = &palette.failure {
+                ui.colored_label(egui::Color32::RED, failure);
+            }
+            egui_palette_errors(ui, palette);
+            egui_palette_preview(ui, palette);
+        }
+
+        ui.separator();
+        egui_export(ui, &mut export_ui, palette_res.as_deref());
+        egui_import(ui, &mut import_ui, &registry);
+    });
+}
+
+// egui_project renders the path of the project file with buttons to save and load it, the
+// resources are only borrowed mutably by a load so that saving doesn't trigger a re-render
+fn egui_project(
+    ui: &mut egui::Ui,
+    project_ui: &mut ProjectUi,
+    expr_list: &mut ResMut<ExprList>,
+    registry: &mut ResMut<ColorSpaceRegistry>,
+) {
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut project_ui.path);
+        let path = Path::new(&project_ui.path);
+        if ui.button("save").clicked() {
+            project_ui.status = Some(
+                save_project(path, expr_list, registry)
+                    .map(|()| format!("saved {}", path.display()))
+                    .map_err(|err| format!("{:#}", err)),
+            );
+        }
+        if ui.button("load").clicked() {
+            project_ui.status = Some(
+                load_project(path, &mut *expr_list, &mut *registry)
+                    .map(|()| format!("loaded {}", path.display()))
+                    .map_err(|err| format!("{:#}", err)),
+            );
+        }
+    });
+
+    egui_status(ui, &project_ui.status);
+}
+
+// egui_export renders the path to export the palette to with a button to export it, the name and
+// key template of each ramp's design tokens, and the layout of exported images
+fn egui_export(ui: &mut egui::Ui, export_ui: &mut ExportUi, palette: Option<&Palette>) {
+    if let Some(palette) = palette {
+        let row_count = palette.rows as usize;
+        let namings = &mut export_ui.options.namings;
+        for y in namings.len()..row_count {
+            namings.push(RampNaming::default_for(y, row_count));
+        }
+        for naming in namings.iter_mut().take(row_count) {
+            ui.horizontal(|ui| {
+                ui.label("ramp");
+                ui.text_edit_singleline(&mut naming.name);
+                ui.label("key");
+                ui.text_edit_singleline(&mut naming.key);
+            });
+        }
+    }
+
+    egui_image_layout(ui, &mut export_ui.options.image_layout);
+
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut export_ui.path);
+        if ui.button("export").clicked() {
+            let path = Path::new(&export_ui.path);
+            export_ui.status = Some(match palette {
+                Some(palette) => export_palette(path, palette, &export_ui.options)
+                    .map(|()| format!("exported {}", path.display()))
+                    .map_err(|err| format!("{:#}", err)),
+                None => Err("no palette has been rendered yet".to_string()),
+            });
+        }
     });
+
+    egui_status(ui, &export_ui.status);
 }
 
-fn egui_expr_row(ui: &mut egui::Ui, expr_row: &mut ExprRow) {
+// egui_image_layout renders the layout of exported images along with its swatch size or width
+fn egui_image_layout(ui: &mut egui::Ui, layout: &mut ImageLayout) {
     ui.horizontal(|ui| {
-        ui.text_edit_singleline(&mut expr_row.var);
-        ui.text_edit_singleline(&mut expr_row.expr);
+        let layout_name = match layout {
+            ImageLayout::Strip(_) => "strip",
+            ImageLayout::Lut(_) => "gradient lut",
+            ImageLayout::Grid(_) => "grid",
+        };
+        egui::ComboBox::from_label("image")
+            .selected_text(layout_name)
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(layout_name == "strip", "strip").clicked() {
+                    *layout = ImageLayout::Strip(1);
+                }
+                if ui.selectable_label(layout_name == "gradient lut", "gradient lut").clicked() {
+                    *layout = ImageLayout::Lut(256);
+                }
+                if ui.selectable_label(layout_name == "grid", "grid").clicked() {
+                    *layout = ImageLayout::Grid(16);
+                }
+            });
+
+        match layout {
+            ImageLayout::Strip(size) | ImageLayout::Grid(size) => {
+                ui.label("swatch px");
+                ui.add(egui::DragValue::new(size).clamp_range(1..=512));
+            }
+            ImageLayout::Lut(width) => {
+                ui.label("width px");
+                ui.add(egui::DragValue::new(width).clamp_range(2..=4096));
+            }
+        }
     });
 }
+
+// egui_import renders the path of a swatch file with a button to import it, followed by each
+// group of swatches imported
+fn egui_import(ui: &mut egui::Ui, import_ui: &mut ImportUi, registry: &ColorSpaceRegistry) {
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut import_ui.path);
+        if ui.button("import").clicked() {
+            let path = Path::new(&import_ui.path);
+            import_ui.status = Some(match import_ase(path, registry) {
+                Ok(groups) => {
+                    import_ui.groups = groups;
+                    Ok(format!("imported {}", path.display()))
+                }
+                Err(err) => Err(format!("{:#}", err)),
+            });
+        }
+    });
+
+    egui_status(ui, &import_ui.status);
+    for group in &import_ui.groups {
+        if !group.name.is_empty() {
+            ui.label(&group.name);
+        }
+        ui.horizontal_wrapped(|ui| {
+            for swatch in &group.swatches {
+                egui_swatch(ui, swatch.preview)
+                    .on_hover_text(format!("{}: {:?}", swatch.name, swatch.color));
+            }
+        });
+    }
+}
+
+// egui_status renders the outcome of the last file operation, errors in red
+fn egui_status(ui: &mut egui::Ui, status: &Option<Result<String, String>>) {
+    match status {
+        Some(Ok(message)) => {
+            ui.label(message);
+        }
+        Some(Err(message)) => {
+            ui.colored_label(egui::Color32::RED, message);
+        }
+        None => (),
+    }
+}
+
+// egui_expr_row renders the variable and expression fields of a row, returning whether either was
+// edited
+fn egui_expr_row(ui: &mut egui::Ui, expr_row: &mut ExprRow) -> bool {
+    ui.horizontal(|ui| {
+        let var_changed = ui.text_edit_singleline(&mut expr_row.var).changed();
+        let expr_changed = egui_expr_field(ui, expr_row, None).changed();
+        var_changed || expr_changed
+    })
+    .inner
+}
+
+// egui_expr_field renders the expression field of a row, underlining the span of its diagnostic
+fn egui_expr_field(
+    ui: &mut egui::Ui,
+    expr_row: &mut ExprRow,
+    hint: Option<String>,
+) -> egui::Response {
+    let span = expr_row
+        .diagnostic
+        .as_ref()
+        .map(|diagnostic| diagnostic.span.clone());
+    let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+        let format = egui::TextFormat {
+            font_id: egui::TextStyle::Body.resolve(ui.style()),
+            color: ui.visuals().text_color(),
+            ..default()
+        };
+        let underlined = egui::TextFormat {
+            underline: egui::Stroke::new(1.5, egui::Color32::RED),
+            ..format.clone()
+        };
+
+        // the text may have been edited since the diagnostic was made, so its span is only used
+        // while it still lands on the text
+        let mut job = egui::text::LayoutJob::default();
+        match span.clone().filter(|span| text.get(span.clone()).is_some()) {
+            Some(span) => {
+                job.append(&text[..span.start], 0.0, format.clone());
+                job.append(&text[span.clone()], 0.0, underlined);
+                job.append(&text[span.end..], 0.0, format);
+            }
+            None => job.append(text, 0.0, format),
+        }
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|fonts| fonts.layout_job(job))
+    };
+
+    let mut field = egui::TextEdit::singleline(&mut expr_row.expr).layouter(&mut layouter);
+    if let Some(hint) = hint {
+        field = field.hint_text(hint);
+    }
+    ui.add(field)
+}
+
+// egui_param renders a slider for the parameter declared by row var, returning whether it was
+// moved. The value is only stored once moved so an untouched parameter follows its default.
+fn egui_param(
+    ui: &mut egui::Ui,
+    params: &mut BTreeMap<String, f64>,
+    var: &str,
+    param: Param,
+) -> bool {
+    let mut value = param.clamp(params.get(var).copied().unwrap_or(param.default));
+    let response = ui.add(egui::Slider::new(&mut value, param.min..=param.max).text(var));
+    if response.changed() {
+        params.insert(var.to_string(), value);
+    }
+
+    response.changed()
+}
+
+// egui_row_diagnostic explains the diagnostic of a row beneath it
+fn egui_row_diagnostic(ui: &mut egui::Ui, expr_row: &ExprRow) {
+    if let Some(diagnostic) = &expr_row.diagnostic {
+        let message = match diagnostic.index {
+            Some(index) => format!("{} (at color {})", diagnostic.message, index),
+            None => diagnostic.message.clone(),
+        };
+        ui.colored_label(egui::Color32::RED, message);
+    }
+}
+
+// egui_row_issues renders each validation issue of a row beneath it
+fn egui_row_issues(ui: &mut egui::Ui, issues: &[RowIssue]) {
+    for issue in issues {
+        let color = match issue.severity {
+            Severity::Warning => egui::Color32::YELLOW,
+            Severity::Error => egui::Color32::RED,
+        };
+        ui.colored_label(color, &issue.message);
+    }
+}
+
+// egui_domain renders the color count and spacing controls for a domain, returning whether either
+// was edited
+fn egui_domain(ui: &mut egui::Ui, domain: &mut Domain) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("colors");
+        let stops = matches!(domain.spacing, Spacing::Stops(_));
+        changed |= ui
+            .add_enabled(
+                !stops,
+                egui::DragValue::new(&mut domain.color_count).clamp_range(COLUMN_RANGE),
+            )
+            .changed();
+
+        let spacing_name = match domain.spacing {
+            Spacing::Uniform => "uniform",
+            Spacing::Power(_) => "power",
+            Spacing::SmoothStep => "smoothstep",
+            Spacing::Stops(_) => "stops",
+        };
+        egui::ComboBox::from_label("spacing")
+            .selected_text(spacing_name)
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(spacing_name == "uniform", "uniform").clicked() {
+                    domain.spacing = Spacing::Uniform;
+                    changed = true;
+                }
+                if ui.selectable_label(spacing_name == "power", "power").clicked() {
+                    domain.spacing = Spacing::Power(2.0);
+                    changed = true;
+                }
+                if ui.selectable_label(spacing_name == "smoothstep", "smoothstep").clicked() {
+                    domain.spacing = Spacing::SmoothStep;
+                    changed = true;
+                }
+            });
+
+        if let Spacing::Power(exponent) = &mut domain.spacing {
+            changed |= ui
+                .add(egui::DragValue::new(exponent).speed(0.05).clamp_range(EXPONENT_RANGE))
+                .changed();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("rows");
+        changed |= ui
+            .add(egui::DragValue::new(&mut domain.row_count).clamp_range(ROW_RANGE))
+            .changed();
+    });
+
+    changed
+}
+
+// SHOWN_PALETTE_ERRORS is how many of the colors that failed to render are explained below the
+// count, the rest are only explained when their swatch is hovered
+const SHOWN_PALETTE_ERRORS: usize = 3;
+
+// egui_palette_errors renders how many colors of the palette failed to render, and why the first
+// of them failed
+fn egui_palette_errors(ui: &mut egui::Ui, palette: &Palette) {
+    if palette.errors.is_empty() {
+        return;
+    }
+
+    ui.colored_label(
+        egui::Color32::RED,
+        format!(
+            "{} of {} colors failed to render",
+            palette.errors.len(),
+            palette.colors.len()
+        ),
+    );
+    for (_, err) in palette.errors.iter().take(SHOWN_PALETTE_ERRORS) {
+        ui.colored_label(egui::Color32::RED, format!("{:#}", err));
+    }
+}
+
+// egui_palette_preview renders a swatch for each color of the palette, including its alpha, with
+// each row of the palette on its own line. Hovering a color that failed to render explains why.
+fn egui_palette_preview(ui: &mut egui::Ui, palette: &Palette) {
+    let errors: BTreeMap<u32, &anyhow::Error> =
+        palette.errors.iter().map(|(index, err)| (*index, err)).collect();
+    let columns = palette.columns.max(1);
+    for (y, row) in palette.rows_iter().enumerate() {
+        ui.horizontal_wrapped(|ui| {
+            for (x, color) in row.iter().enumerate() {
+                let response = egui_swatch(ui, *color);
+                if let Some(err) = errors.get(&(y as u32 * columns + x as u32)) {
+                    response.on_hover_text(format!("{:#}", err));
+                }
+            }
+        });
+    }
+}
+
+// egui_swatch renders a square of color, including its alpha
+fn egui_swatch(ui: &mut egui::Ui, color: RgbaColor) -> egui::Response {
+    let [red, green, blue, alpha] = color.to_u8();
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(24.0, 24.0), egui::Sense::hover());
+    ui.painter().rect_filled(
+        rect,
+        0.0,
+        egui::Color32::from_rgba_unmultiplied(red, green, blue, alpha),
+    );
+
+    response
+}